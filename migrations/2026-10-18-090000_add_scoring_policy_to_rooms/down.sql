@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE
+    rooms
+DROP
+    judge_count,
+DROP
+    drop_count,
+DROP
+    aggregation_mode;
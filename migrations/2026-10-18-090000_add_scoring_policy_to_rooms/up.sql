@@ -0,0 +1,9 @@
+-- Your SQL goes here
+ALTER TABLE
+    rooms
+ADD
+    judge_count INTEGER NOT NULL DEFAULT 5,
+ADD
+    drop_count INTEGER NOT NULL DEFAULT 1,
+ADD
+    aggregation_mode TEXT NOT NULL DEFAULT 'trimmed_sum';
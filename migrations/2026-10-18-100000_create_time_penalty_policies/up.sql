@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TABLE time_penalty_policies (
+    id TEXT PRIMARY KEY NOT NULL,
+    room_id TEXT REFERENCES rooms(id) ON DELETE CASCADE NOT NULL,
+    round_id TEXT REFERENCES rounds(id) ON DELETE CASCADE,
+    time_limit_seconds INTEGER NOT NULL,
+    grace_seconds INTEGER NOT NULL,
+    increment_seconds INTEGER NOT NULL,
+    penalty_per_increment REAL NOT NULL,
+    penalty_cap REAL,
+    no_penalty BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE UNIQUE INDEX time_penalty_policies_room_default ON time_penalty_policies (room_id)
+WHERE
+    round_id IS NULL;
+
+CREATE UNIQUE INDEX time_penalty_policies_round ON time_penalty_policies (round_id)
+WHERE
+    round_id IS NOT NULL;
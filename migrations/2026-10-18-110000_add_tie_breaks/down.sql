@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE coin_flips;
+
+ALTER TABLE
+    rooms
+DROP
+    tie_break_method;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+ALTER TABLE
+    rooms
+ADD
+    tie_break_method TEXT NOT NULL DEFAULT 'high_low_coin';
+
+CREATE TABLE coin_flips (
+    id TEXT PRIMARY KEY NOT NULL,
+    room_id TEXT REFERENCES rooms(id) ON DELETE CASCADE NOT NULL,
+    scope_id TEXT NOT NULL,
+    participant_id TEXT REFERENCES participants(id) ON DELETE CASCADE NOT NULL,
+    draw INTEGER NOT NULL,
+    created TEXT NOT NULL,
+    UNIQUE (scope_id, participant_id)
+);
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE
+    rooms
+ADD
+    cut_sizes INTEGER [] NOT NULL DEFAULT '{}';
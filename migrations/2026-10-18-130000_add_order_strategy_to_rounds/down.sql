@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE
+    rounds
+DROP
+    order_strategy,
+DROP
+    order_seed;
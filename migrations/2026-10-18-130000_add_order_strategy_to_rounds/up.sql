@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE
+    rounds
+ADD
+    order_strategy TEXT NOT NULL DEFAULT 'manual',
+ADD
+    order_seed BIGINT;
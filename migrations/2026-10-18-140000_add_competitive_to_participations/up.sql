@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE
+    participations
+ADD
+    competitive BOOLEAN NOT NULL DEFAULT TRUE;
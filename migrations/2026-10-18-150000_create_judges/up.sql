@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE judges (
+    id TEXT PRIMARY KEY NOT NULL,
+    room_id TEXT REFERENCES rooms(id) ON DELETE CASCADE NOT NULL,
+    seat INTEGER NOT NULL,
+    label TEXT NOT NULL,
+    UNIQUE (room_id, seat)
+);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE
+    rooms
+DROP
+    score_min,
+DROP
+    score_max,
+DROP
+    score_precision;
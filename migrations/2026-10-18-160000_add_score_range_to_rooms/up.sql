@@ -0,0 +1,9 @@
+-- Your SQL goes here
+ALTER TABLE
+    rooms
+ADD
+    score_min REAL NOT NULL DEFAULT 0,
+ADD
+    score_max REAL NOT NULL DEFAULT 10,
+ADD
+    score_precision INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE rounds DROP CONSTRAINT rounds_room_id_round_number_key;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE rounds ADD CONSTRAINT rounds_room_id_round_number_key UNIQUE (room_id, round_number);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE scores DROP CONSTRAINT scores_participation_id_submitter_id_key;
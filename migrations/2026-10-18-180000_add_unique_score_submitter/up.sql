@@ -0,0 +1,57 @@
+-- Your SQL goes here
+-- A judge who submitted twice keeps the score written last. Scores carry no
+-- insert time, so the later heap position stands in for it.
+CREATE TEMPORARY TABLE rescored_participations (id TEXT PRIMARY KEY NOT NULL) ON COMMIT DROP;
+
+WITH removed AS (
+    DELETE FROM scores a
+        USING scores b
+        WHERE a.participation_id = b.participation_id
+        AND a.submitter_id = b.submitter_id
+        AND a.ctid < b.ctid
+        RETURNING a.participation_id
+)
+INSERT INTO rescored_participations (id)
+SELECT DISTINCT participation_id FROM removed;
+
+-- The participations that lost a score are aggregated again the way the server
+-- does it: only seated judges count, and the score stays unset until every
+-- expected score is in.
+WITH counted AS (
+    SELECT
+        scores.participation_id,
+        scores.value,
+        rooms.aggregation_mode,
+        rooms.drop_count,
+        least(
+            rooms.judge_count,
+            (SELECT count(*) FROM judges seated WHERE seated.room_id = rooms.id AND seated.seat <= rooms.judge_count)
+        ) AS expected,
+        count(*) OVER (PARTITION BY scores.participation_id) AS scored,
+        row_number() OVER (PARTITION BY scores.participation_id ORDER BY scores.value) AS position
+    FROM scores
+    JOIN participations ON participations.id = scores.participation_id
+    JOIN rounds ON rounds.id = participations.round_id
+    JOIN rooms ON rooms.id = rounds.room_id
+    JOIN judges ON judges.id = scores.submitter_id AND judges.room_id = rooms.id AND judges.seat <= rooms.judge_count
+    WHERE scores.participation_id IN (SELECT id FROM rescored_participations)
+),
+aggregated AS (
+    SELECT
+        participation_id,
+        CASE
+            WHEN min(expected) = 0 OR min(scored) < min(expected) THEN NULL
+            WHEN min(aggregation_mode) = 'sum' THEN sum(value)
+            WHEN min(aggregation_mode) = 'mean' THEN avg(value)
+            ELSE coalesce(sum(value) FILTER (WHERE position > drop_count AND position <= scored - drop_count), 0)
+        END AS score
+    FROM counted
+    GROUP BY participation_id
+)
+UPDATE participations
+    SET score = aggregated.score
+    FROM rescored_participations
+    LEFT JOIN aggregated ON aggregated.participation_id = rescored_participations.id
+    WHERE participations.id = rescored_participations.id;
+
+ALTER TABLE scores ADD CONSTRAINT scores_participation_id_submitter_id_key UNIQUE (participation_id, submitter_id);
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE rooms DROP CONSTRAINT rooms_participation_id_current_fkey;
+
+ALTER TABLE rooms ADD CONSTRAINT rooms_participation_id_current_fkey
+    FOREIGN KEY (participation_id_current) REFERENCES participations(id) ON DELETE SET NULL;
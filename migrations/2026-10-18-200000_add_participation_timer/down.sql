@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE participations
+    DROP COLUMN timer_started_at,
+    DROP COLUMN timer_stopped_at;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE participations
+    ADD COLUMN timer_started_at TEXT,
+    ADD COLUMN timer_stopped_at TEXT;
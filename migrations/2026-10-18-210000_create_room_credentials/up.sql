@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE room_credentials (
+    id TEXT PRIMARY KEY NOT NULL,
+    room_id TEXT REFERENCES rooms(id) ON DELETE CASCADE NOT NULL,
+    role TEXT NOT NULL,
+    judge_id TEXT REFERENCES judges(id) ON DELETE CASCADE,
+    label TEXT,
+    token_hash TEXT NOT NULL UNIQUE,
+    created TEXT NOT NULL
+);
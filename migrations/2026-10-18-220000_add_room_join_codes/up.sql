@@ -0,0 +1,31 @@
+-- Your SQL goes here
+ALTER TABLE rooms ADD COLUMN join_code TEXT;
+ALTER TABLE rooms ADD CONSTRAINT rooms_join_code_key UNIQUE (join_code);
+
+-- Existing rooms get codes from the alphabet the server draws from (no 0/O or
+-- 1/I), drawing again on the rare collision.
+DO $$
+DECLARE
+    alphabet CONSTANT TEXT := 'ABCDEFGHJKLMNPQRSTUVWXYZ23456789';
+    code_length CONSTANT INTEGER := 6;
+    room_id_value TEXT;
+    candidate TEXT;
+BEGIN
+    FOR room_id_value IN SELECT id FROM rooms WHERE join_code IS NULL LOOP
+        LOOP
+            candidate := '';
+            FOR position IN 1..code_length LOOP
+                candidate := candidate || substr(alphabet, 1 + floor(random() * length(alphabet))::INTEGER, 1);
+            END LOOP;
+            BEGIN
+                UPDATE rooms SET join_code = candidate WHERE id = room_id_value;
+                EXIT;
+            EXCEPTION WHEN unique_violation THEN
+                NULL;
+            END;
+        END LOOP;
+    END LOOP;
+END
+$$;
+
+ALTER TABLE rooms ALTER COLUMN join_code SET NOT NULL;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE rooms DROP COLUMN organizer_id;
+DROP TABLE organizer_sessions;
+DROP TABLE organizers;
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE organizers (
+    id TEXT PRIMARY KEY NOT NULL,
+    email TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    created TEXT NOT NULL
+);
+
+CREATE TABLE organizer_sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    organizer_id TEXT REFERENCES organizers(id) ON DELETE CASCADE NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+-- Rooms made before organizer accounts have no owner and stay reachable only
+-- through their credentials. Deleting an organizer leaves their rooms unowned
+-- in the same way rather than taking the event data with them.
+ALTER TABLE rooms ADD COLUMN organizer_id TEXT REFERENCES organizers(id) ON DELETE SET NULL;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE judges DROP COLUMN organization_id;
+ALTER TABLE participants DROP COLUMN organization_id;
+ALTER TABLE rooms DROP COLUMN organization_id;
+DROP TABLE organization_members;
+DROP TABLE organizations;
@@ -0,0 +1,34 @@
+-- Your SQL goes here
+CREATE TABLE organizations (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    created TEXT NOT NULL
+);
+
+CREATE TABLE organization_members (
+    id TEXT PRIMARY KEY NOT NULL,
+    organization_id TEXT REFERENCES organizations(id) ON DELETE CASCADE NOT NULL,
+    organizer_id TEXT REFERENCES organizers(id) ON DELETE CASCADE NOT NULL,
+    role TEXT NOT NULL,
+    created TEXT NOT NULL,
+    UNIQUE (organization_id, organizer_id)
+);
+
+ALTER TABLE rooms ADD COLUMN organization_id TEXT REFERENCES organizations(id) ON DELETE CASCADE;
+ALTER TABLE participants ADD COLUMN organization_id TEXT REFERENCES organizations(id) ON DELETE CASCADE;
+ALTER TABLE judges ADD COLUMN organization_id TEXT REFERENCES organizations(id) ON DELETE CASCADE;
+
+-- Every organizer who already owns rooms gets an organization of their own to
+-- hold them. It reuses the organizer's id, which keeps the backfill a plain join.
+INSERT INTO organizations (id, name, created)
+SELECT organizers.id, organizers.name, organizers.created
+FROM organizers
+WHERE EXISTS (SELECT 1 FROM rooms WHERE rooms.organizer_id = organizers.id);
+
+INSERT INTO organization_members (id, organization_id, organizer_id, role, created)
+SELECT organizations.id, organizations.id, organizations.id, 'owner', organizations.created
+FROM organizations;
+
+UPDATE rooms SET organization_id = organizer_id WHERE organizer_id IS NOT NULL;
+UPDATE participants SET organization_id = rooms.organization_id FROM rooms WHERE rooms.id = participants.room_id;
+UPDATE judges SET organization_id = rooms.organization_id FROM rooms WHERE rooms.id = judges.room_id;
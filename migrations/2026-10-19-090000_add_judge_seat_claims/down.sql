@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE judges DROP COLUMN token_nonce;
+ALTER TABLE judges DROP COLUMN claimed_at;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE judges ADD COLUMN claimed_at TEXT;
+ALTER TABLE judges ADD COLUMN token_nonce TEXT;
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE
+    scores
+ALTER COLUMN
+    value TYPE REAL USING value / 1000.0;
+
+ALTER TABLE
+    participations
+ALTER COLUMN
+    score TYPE REAL USING score / 1000.0;
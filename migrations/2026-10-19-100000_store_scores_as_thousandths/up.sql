@@ -0,0 +1,10 @@
+-- Your SQL goes here
+ALTER TABLE
+    scores
+ALTER COLUMN
+    value TYPE INTEGER USING ROUND(value * 1000)::INTEGER;
+
+ALTER TABLE
+    participations
+ALTER COLUMN
+    score TYPE INTEGER USING ROUND(score * 1000)::INTEGER;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE
+    participants
+ADD
+    competitive BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,572 @@
+use std::{ convert::Infallible, env, sync::Arc };
+use argon2::{ password_hash::{ PasswordHash, PasswordHasher, PasswordVerifier, SaltString }, Argon2 };
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{ header::AUTHORIZATION, request::Parts },
+};
+use chrono::{ DateTime, Duration, Utc };
+use hmac::{ Hmac, Mac };
+use sha2::{ Digest, Sha256 };
+use uuid::Uuid;
+use crate::{ error::AppError, models::{ OrganizationMember, RoomCredential } };
+
+pub const JOIN_CODE_LENGTH: usize = 6;
+/// No 0/O or 1/I, so codes survive being read aloud or off a projector.
+const JOIN_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+pub const DEFAULT_JUDGE_TOKEN_TTL_SECONDS: i64 = 12 * 60 * 60;
+/// Shorter secrets make the HMAC key guessable.
+pub const MIN_JUDGE_TOKEN_SECRET_LENGTH: usize = 32;
+const JUDGE_TOKEN_VERSION: &str = "j2";
+
+pub const SESSION_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+const SESSION_TOKEN_PREFIX: &str = "s1.";
+
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+/// Argon2 has to hash whatever it is given, so very long passwords are refused.
+pub const MAX_PASSWORD_LENGTH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Host,
+    Judge,
+    Timekeeper,
+    Audience,
+}
+
+impl Role {
+    pub fn parse(value: &str) -> Option<Role> {
+        return match value {
+            "host" => Some(Role::Host),
+            "judge" => Some(Role::Judge),
+            "timekeeper" => Some(Role::Timekeeper),
+            "audience" => Some(Role::Audience),
+            _ => None,
+        };
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            Role::Host => "host",
+            Role::Judge => "judge",
+            Role::Timekeeper => "timekeeper",
+            Role::Audience => "audience",
+        };
+    }
+}
+
+pub fn validate_role(value: &str) -> Result<Role, AppError> {
+    return Role::parse(value).ok_or_else(||
+        AppError::validation(format!("role must be one of host, judge, timekeeper, audience (got {})", value))
+    );
+}
+
+/// Anyone with a credential for the room, e.g. to read it or follow its events.
+pub const EVERYONE: &[Role] = &[Role::Host, Role::Judge, Role::Timekeeper, Role::Audience];
+pub const HOST: &[Role] = &[Role::Host];
+pub const TIMEKEEPERS: &[Role] = &[Role::Host, Role::Timekeeper];
+pub const SCORERS: &[Role] = &[Role::Host, Role::Judge];
+
+/// An organizer's standing in an organization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberRole {
+    /// Runs rooms and also manages who else is a member.
+    Owner,
+    Organizer,
+    /// Follows the organization's rooms without changing anything.
+    Viewer,
+}
+
+impl MemberRole {
+    pub fn parse(value: &str) -> Option<MemberRole> {
+        return match value {
+            "owner" => Some(MemberRole::Owner),
+            "organizer" => Some(MemberRole::Organizer),
+            "viewer" => Some(MemberRole::Viewer),
+            _ => None,
+        };
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            MemberRole::Owner => "owner",
+            MemberRole::Organizer => "organizer",
+            MemberRole::Viewer => "viewer",
+        };
+    }
+
+    /// What a member may do in each of the organization's rooms.
+    pub fn room_role(&self) -> Role {
+        return match self {
+            MemberRole::Owner | MemberRole::Organizer => Role::Host,
+            MemberRole::Viewer => Role::Audience,
+        };
+    }
+}
+
+pub fn validate_member_role(value: &str) -> Result<MemberRole, AppError> {
+    return MemberRole::parse(value).ok_or_else(||
+        AppError::validation(format!("role must be one of owner, organizer, viewer (got {})", value))
+    );
+}
+
+pub const ORG_MEMBERS: &[MemberRole] = &[MemberRole::Owner, MemberRole::Organizer, MemberRole::Viewer];
+pub const ORG_ORGANIZERS: &[MemberRole] = &[MemberRole::Owner, MemberRole::Organizer];
+pub const ORG_OWNERS: &[MemberRole] = &[MemberRole::Owner];
+
+impl OrganizationMember {
+    /// Unknown roles get the least access rather than failing every request.
+    pub fn role(&self) -> MemberRole {
+        return MemberRole::parse(&self.role).unwrap_or(MemberRole::Viewer);
+    }
+}
+
+/// Who a request acts as, whether it came with a stored credential, a signed
+/// judge token or an organizer session. `organization_id` is the tenant whose
+/// data the request may see; rooms from before organizations have none.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub room_id: String,
+    pub role: Role,
+    pub judge_id: Option<String>,
+    pub organization_id: Option<String>,
+}
+
+impl Caller {
+    pub fn tenant(&self) -> Option<&str> {
+        return self.organization_id.as_deref();
+    }
+}
+
+impl RoomCredential {
+    /// Unknown roles get the least access rather than failing every request.
+    pub fn role(&self) -> Role {
+        return Role::parse(&self.role).unwrap_or(Role::Audience);
+    }
+}
+
+/// Checks an already authenticated caller against a room and the roles an
+/// action allows.
+pub fn check_access(caller: &Caller, room_id: &str, roles: &[Role]) -> Result<(), AppError> {
+    if caller.room_id != room_id {
+        return Err(AppError::Forbidden(format!("this credential is not valid for room {}", room_id)));
+    }
+    if !roles.contains(&caller.role) {
+        return Err(AppError::Forbidden(format!("the {} role is not allowed to do this", caller.role.as_str())));
+    }
+    return Ok(());
+}
+
+/// A fresh bearer token, 244 random bits from two v4 UUIDs. Only its hash is stored.
+pub fn generate_token() -> String {
+    return format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+}
+
+pub fn hash_token(token: &str) -> String {
+    return format!("{:x}", Sha256::digest(token.as_bytes()));
+}
+
+pub fn generate_join_code() -> String {
+    let base = JOIN_CODE_ALPHABET.len() as u128;
+    let mut bits = Uuid::new_v4().as_u128();
+    let mut code = String::with_capacity(JOIN_CODE_LENGTH);
+    for _ in 0..JOIN_CODE_LENGTH {
+        code.push(JOIN_CODE_ALPHABET[(bits % base) as usize] as char);
+        bits /= base;
+    }
+    return code;
+}
+
+/// Join codes are typed by people, so case and stray spaces don't matter.
+pub fn normalize_join_code(code: &str) -> String {
+    return code.trim().to_uppercase();
+}
+
+/// A bearer token for a signed-in organizer. The prefix tells it apart from
+/// room credentials without a lookup.
+pub fn generate_session_token() -> String {
+    return format!("{}{}", SESSION_TOKEN_PREFIX, generate_token());
+}
+
+pub fn is_session_token(token: &str) -> bool {
+    return token.starts_with(SESSION_TOKEN_PREFIX);
+}
+
+pub fn session_expiry() -> DateTime<Utc> {
+    return Utc::now() + Duration::seconds(SESSION_TTL_SECONDS);
+}
+
+/// Emails are compared case-insensitively, so they are stored lowercased.
+pub fn validate_email(email: &str) -> Result<String, AppError> {
+    let email = email.trim().to_lowercase();
+    let valid = email
+        .split_once('@')
+        .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.') && !email.contains(char::is_whitespace));
+    if !valid {
+        return Err(AppError::validation("email must be an email address"));
+    }
+    return Ok(email);
+}
+
+pub fn validate_password(password: &str) -> Result<(), AppError> {
+    let length = password.chars().count();
+    if length < MIN_PASSWORD_LENGTH {
+        return Err(AppError::validation(format!("password must be at least {} characters", MIN_PASSWORD_LENGTH)));
+    }
+    if length > MAX_PASSWORD_LENGTH {
+        return Err(AppError::validation(format!("password must be at most {} characters", MAX_PASSWORD_LENGTH)));
+    }
+    return Ok(());
+}
+
+/// An Argon2id hash in PHC string form, which carries its own salt and parameters.
+pub fn hash_password(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())
+        .map_err(|error| AppError::Internal(format!("could not encode salt: {}", error)))?;
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|error| AppError::Internal(format!("could not hash password: {}", error)))?;
+    return Ok(hash.to_string());
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    return PasswordHash::new(password_hash).is_ok_and(|hash|
+        Argon2::default().verify_password(password.as_bytes(), &hash).is_ok()
+    );
+}
+
+/// What a verified judge token vouches for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JudgeClaims {
+    pub judge_id: String,
+    pub join_code: String,
+    pub nonce: String,
+    pub expires_at: i64,
+}
+
+/// How judge tokens are signed and how long they last. Read once at startup.
+pub struct JudgeTokens {
+    key: Vec<u8>,
+    ttl: Duration,
+}
+
+impl JudgeTokens {
+    pub fn new(key: Vec<u8>, ttl: Duration) -> JudgeTokens {
+        return JudgeTokens { key, ttl };
+    }
+
+    /// From `JUDGE_TOKEN_SECRET`, which is required, and `JUDGE_TOKEN_TTL_SECONDS`,
+    /// which overrides the default lifetime.
+    pub fn from_env() -> Result<JudgeTokens, String> {
+        let secret = env::var("JUDGE_TOKEN_SECRET").map_err(|_| "JUDGE_TOKEN_SECRET must be set".to_owned())?;
+        if secret.len() < MIN_JUDGE_TOKEN_SECRET_LENGTH {
+            return Err(format!("JUDGE_TOKEN_SECRET must be at least {} characters", MIN_JUDGE_TOKEN_SECRET_LENGTH));
+        }
+        let ttl_seconds = match env::var("JUDGE_TOKEN_TTL_SECONDS") {
+            Ok(value) =>
+                value
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|seconds| *seconds > 0)
+                    .ok_or_else(|| format!("JUDGE_TOKEN_TTL_SECONDS must be a positive number (got {})", value))?,
+            Err(_) => DEFAULT_JUDGE_TOKEN_TTL_SECONDS,
+        };
+        return Ok(JudgeTokens::new(secret.into_bytes(), Duration::seconds(ttl_seconds)));
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes a key of any length");
+        mac.update(payload.as_bytes());
+        return mac;
+    }
+
+    /// Signs `j2.<judge id>.<join code>.<nonce>.<expiry>`. The join code is part
+    /// of what is signed, so rotating a room's code revokes every token issued
+    /// under it; the nonce ties the token to one claim of the seat.
+    pub fn sign(&self, judge_id: &str, join_code: &str, nonce: &str) -> (String, DateTime<Utc>) {
+        let expires_at = Utc::now() + self.ttl;
+        let payload = format!(
+            "{}.{}.{}.{}.{}",
+            JUDGE_TOKEN_VERSION,
+            judge_id,
+            join_code,
+            nonce,
+            expires_at.timestamp()
+        );
+        let signature = format!("{:x}", self.mac(&payload).finalize().into_bytes());
+        return (format!("{}.{}", payload, signature), expires_at);
+    }
+
+    /// Checks the signature and expiry. Whether the judge is still seated and
+    /// the join code still current is up to the caller.
+    pub fn verify(&self, token: &str) -> Result<JudgeClaims, AppError> {
+        let invalid = || AppError::Unauthorized("judge token is invalid".to_owned());
+        let (payload, signature) = token.rsplit_once('.').ok_or_else(invalid)?;
+        let signature = decode_hex(signature).ok_or_else(invalid)?;
+        self.mac(payload).verify_slice(&signature).map_err(|_| invalid())?;
+
+        let parts: Vec<&str> = payload.split('.').collect();
+        let [_, judge_id, join_code, nonce, expires_at] = parts[..] else {
+            return Err(invalid());
+        };
+        let expires_at: i64 = expires_at.parse().map_err(|_| invalid())?;
+        if expires_at < Utc::now().timestamp() {
+            return Err(AppError::Unauthorized("judge token has expired; join the room again".to_owned()));
+        }
+
+        return Ok(JudgeClaims {
+            judge_id: judge_id.to_owned(),
+            join_code: join_code.to_owned(),
+            nonce: nonce.to_owned(),
+            expires_at,
+        });
+    }
+}
+
+pub fn is_judge_token(token: &str) -> bool {
+    return token.starts_with(&format!("{}.", JUDGE_TOKEN_VERSION));
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    return (0..value.len())
+        .step_by(2)
+        .map(|pos| u8::from_str_radix(value.get(pos..pos + 2)?, 16).ok())
+        .collect();
+}
+
+/// The bearer token a request was made with, from `Authorization: Bearer ...`,
+/// along with what is needed to check judge tokens.
+#[derive(Clone)]
+pub struct AccessToken {
+    value: Option<String>,
+    judge_tokens: Arc<JudgeTokens>,
+}
+
+impl AccessToken {
+    pub fn new(value: Option<String>, judge_tokens: Arc<JudgeTokens>) -> AccessToken {
+        return AccessToken { value, judge_tokens };
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        return self.value.as_deref();
+    }
+
+    pub fn judge_tokens(&self) -> &JudgeTokens {
+        return &self.judge_tokens;
+    }
+
+    /// Falls back to a `?token=` query parameter. Only the websocket and event
+    /// stream routes use this, since browsers can't set headers on them;
+    /// anywhere else it would leave tokens in access logs and browser history.
+    pub fn or_query(self, token: Option<String>) -> AccessToken {
+        return AccessToken { value: self.value.or(token), ..self };
+    }
+}
+
+/// Router state that knows how judge tokens are signed.
+pub trait JudgeTokenState {
+    fn judge_tokens(&self) -> Arc<JudgeTokens>;
+}
+
+impl<T: JudgeTokenState> JudgeTokenState for Arc<T> {
+    fn judge_tokens(&self) -> Arc<JudgeTokens> {
+        return T::judge_tokens(self);
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AccessToken where S: JudgeTokenState + Send + Sync {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<AccessToken, Infallible> {
+        let header = parts.headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_owned());
+        return Ok(AccessToken::new(header, state.judge_tokens()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credential(room_id: &str, role: Role) -> RoomCredential {
+        return RoomCredential {
+            id: "credential".to_owned(),
+            room_id: room_id.to_owned(),
+            role: role.as_str().to_owned(),
+            judge_id: None,
+            label: None,
+            token_hash: hash_token("token"),
+            created: "2024-05-01T20:00:00Z".to_owned(),
+        };
+    }
+
+    fn caller(room_id: &str, role: Role) -> Caller {
+        return Caller { room_id: room_id.to_owned(), role, judge_id: None, organization_id: None };
+    }
+
+    fn judge_tokens(ttl: Duration) -> JudgeTokens {
+        return JudgeTokens::new(vec![7; MIN_JUDGE_TOKEN_SECRET_LENGTH], ttl);
+    }
+
+    /// Signs `payload` the way `JudgeTokens::sign` does, for tokens it won't produce.
+    fn signed(tokens: &JudgeTokens, payload: &str) -> String {
+        return format!("{}.{:x}", payload, tokens.mac(payload).finalize().into_bytes());
+    }
+
+    struct TestState(Arc<JudgeTokens>);
+
+    impl JudgeTokenState for TestState {
+        fn judge_tokens(&self) -> Arc<JudgeTokens> {
+            return self.0.clone();
+        }
+    }
+
+    #[test]
+    fn roles_round_trip_and_unknown_roles_are_rejected() {
+        for role in EVERYONE {
+            assert_eq!(validate_role(role.as_str()).unwrap(), *role);
+        }
+        assert!(matches!(validate_role("admin"), Err(AppError::Validation { .. })));
+    }
+
+    #[test]
+    fn unknown_stored_roles_only_get_audience_access() {
+        let mut stored = credential("room-1", Role::Host);
+        stored.role = "admin".to_owned();
+        assert_eq!(stored.role(), Role::Audience);
+        let stored = caller(&stored.room_id, stored.role());
+        assert!(check_access(&stored, "room-1", EVERYONE).is_ok());
+        assert!(check_access(&stored, "room-1", HOST).is_err());
+    }
+
+    #[test]
+    fn access_is_limited_to_the_room_and_roles() {
+        let judge = caller("room-1", Role::Judge);
+        assert!(check_access(&judge, "room-1", SCORERS).is_ok());
+        assert!(matches!(check_access(&judge, "room-1", HOST), Err(AppError::Forbidden(_))));
+        assert!(matches!(check_access(&judge, "room-1", TIMEKEEPERS), Err(AppError::Forbidden(_))));
+        assert!(matches!(check_access(&judge, "room-2", SCORERS), Err(AppError::Forbidden(_))));
+    }
+
+    #[test]
+    fn tokens_are_random_and_hashed_to_stable_hex() {
+        assert_ne!(generate_token(), generate_token());
+        let hash = hash_token("secret");
+        assert_eq!(hash, hash_token("secret"));
+        assert_ne!(hash, hash_token("secret2"));
+        assert_eq!(hash.len(), 64);
+    }
+
+    #[test]
+    fn signed_judge_token_verifies() {
+        let tokens = judge_tokens(Duration::hours(1));
+        let (token, expires_at) = tokens.sign("judge-1", "ABC234", "nonce-1");
+        assert!(is_judge_token(&token));
+        assert!(!is_judge_token(&generate_token()));
+
+        let claims = tokens.verify(&token).unwrap();
+        assert_eq!(claims.judge_id, "judge-1");
+        assert_eq!(claims.join_code, "ABC234");
+        assert_eq!(claims.nonce, "nonce-1");
+        assert_eq!(claims.expires_at, expires_at.timestamp());
+    }
+
+    #[test]
+    fn expired_judge_token_is_rejected() {
+        let tokens = judge_tokens(Duration::seconds(-60));
+        let (token, _) = tokens.sign("judge-1", "ABC234", "nonce-1");
+        let error = tokens.verify(&token).unwrap_err();
+        assert!(matches!(error, AppError::Unauthorized(message) if message.contains("expired")));
+    }
+
+    #[test]
+    fn tampered_judge_token_is_rejected() {
+        let tokens = judge_tokens(Duration::hours(1));
+        let (token, _) = tokens.sign("judge-1", "ABC234", "nonce-1");
+        let forged = token.replacen("judge-1", "judge-2", 1);
+        assert!(matches!(tokens.verify(&forged), Err(AppError::Unauthorized(_))));
+        assert!(tokens.verify("j2.judge-1").is_err());
+        assert!(tokens.verify(&format!("{}zz", token)).is_err());
+        assert!(tokens.verify(&signed(&tokens, "j2.judge-1.ABC234.nonce-1")).is_err());
+
+        let other_key = JudgeTokens::new(vec![8; MIN_JUDGE_TOKEN_SECRET_LENGTH], Duration::hours(1));
+        assert!(other_key.verify(&token).is_err());
+    }
+
+    #[test]
+    fn join_codes_avoid_ambiguous_characters() {
+        for _ in 0..100 {
+            let code = generate_join_code();
+            assert_eq!(code.len(), JOIN_CODE_LENGTH);
+            assert!(code.bytes().all(|byte| JOIN_CODE_ALPHABET.contains(&byte)));
+        }
+        assert_eq!(normalize_join_code(" abc234 "), "ABC234");
+    }
+
+    #[test]
+    fn session_tokens_are_told_apart_from_room_credentials() {
+        assert!(is_session_token(&generate_session_token()));
+        assert!(!is_session_token(&generate_token()));
+        assert!(!is_judge_token(&generate_session_token()));
+    }
+
+    #[test]
+    fn passwords_hash_with_a_salt_and_verify() {
+        let hash = hash_password("correct horse").unwrap();
+        assert_ne!(hash, hash_password("correct horse").unwrap());
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("battery staple", &hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+    }
+
+    #[test]
+    fn email_and_password_rules() {
+        assert_eq!(validate_email(" Host@Example.com ").unwrap(), "host@example.com");
+        assert!(validate_email("host@localhost").is_err());
+        assert!(validate_email("@example.com").is_err());
+        assert!(validate_password("short").is_err());
+        assert!(validate_password(&"x".repeat(MAX_PASSWORD_LENGTH + 1)).is_err());
+        assert!(validate_password("long enough").is_ok());
+    }
+
+    #[test]
+    fn member_roles_map_to_room_roles() {
+        for member_role in ORG_MEMBERS {
+            assert_eq!(validate_member_role(member_role.as_str()).unwrap(), *member_role);
+        }
+        assert!(validate_member_role("admin").is_err());
+        assert_eq!(MemberRole::Owner.room_role(), Role::Host);
+        assert_eq!(MemberRole::Organizer.room_role(), Role::Host);
+        assert_eq!(MemberRole::Viewer.room_role(), Role::Audience);
+    }
+
+    async fn extract(uri: &str, authorization: Option<&str>) -> AccessToken {
+        let mut request = axum::http::Request::builder().uri(uri);
+        if let Some(authorization) = authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        let state = TestState(Arc::new(judge_tokens(Duration::hours(1))));
+        return AccessToken::from_request_parts(&mut parts, &state).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn access_token_comes_from_the_header_only() {
+        assert_eq!(extract("/room", Some("Bearer secret")).await.value(), Some("secret"));
+        assert_eq!(extract("/room?token=leaked", None).await.value(), None);
+        assert_eq!(extract("/room", Some("Basic secret")).await.value(), None);
+
+        let streamed = extract("/room", None).await.or_query(Some("query".to_owned()));
+        assert_eq!(streamed.value(), Some("query"));
+        let preferred = extract("/room", Some("Bearer header")).await.or_query(Some("query".to_owned()));
+        assert_eq!(preferred.value(), Some("header"));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{ error::AppError, models::{ Participant, Standing } };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStrategy {
+    Manual,
+    Random,
+    Standings,
+    ReverseStandings,
+}
+
+impl OrderStrategy {
+    pub fn parse(value: &str) -> Option<OrderStrategy> {
+        return match value {
+            "manual" => Some(OrderStrategy::Manual),
+            "random" => Some(OrderStrategy::Random),
+            "standings" => Some(OrderStrategy::Standings),
+            "reverse_standings" => Some(OrderStrategy::ReverseStandings),
+            _ => None,
+        };
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            OrderStrategy::Manual => "manual",
+            OrderStrategy::Random => "random",
+            OrderStrategy::Standings => "standings",
+            OrderStrategy::ReverseStandings => "reverse_standings",
+        };
+    }
+}
+
+pub fn validate_order_strategy(value: &str) -> Result<OrderStrategy, AppError> {
+    return OrderStrategy::parse(value).ok_or_else(||
+        AppError::validation(
+            format!("order must be one of manual, random, standings, reverse_standings (got {})", value)
+        )
+    );
+}
+
+/// Cut sizes are the field size of each round, starting with round 1,
+/// so a 12 → 6 → 3 bout is stored as `[12, 6, 3]`.
+pub fn validate_cut_sizes(cut_sizes: &[i32]) -> Result<(), AppError> {
+    if cut_sizes.iter().any(|size| *size < 1) {
+        return Err(AppError::validation("cut_sizes must all be at least 1"));
+    }
+    if cut_sizes.windows(2).any(|pair| pair[1] > pair[0]) {
+        return Err(AppError::validation("cut_sizes must not grow from one round to the next"));
+    }
+    return Ok(());
+}
+
+/// Takes the top `size` poets from a round's standings. Fails rather than
+/// guessing when the round isn't fully scored or a tie straddles the cut line.
+pub fn select_cut(standings: &[Standing], size: usize) -> Result<Vec<Participant>, AppError> {
+    if standings.iter().any(|standing| standing.rank.is_none()) {
+        return Err(AppError::Conflict("previous round has not been fully scored".to_owned()));
+    }
+    if size < standings.len() {
+        let last_in = &standings[size - 1];
+        let first_out = &standings[size];
+        if last_in.rank == first_out.rank {
+            return Err(
+                AppError::Conflict(
+                    format!(
+                        "{} and {} are tied at the cut line and the room's tie-break method cannot separate them",
+                        last_in.participation.participant.name,
+                        first_out.participation.participant.name
+                    )
+                )
+            );
+        }
+    }
+
+    let results = standings
+        .iter()
+        .take(size)
+        .map(|standing| standing.participation.participant.clone())
+        .collect();
+
+    return Ok(results);
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    return z ^ (z >> 31);
+}
+
+/// Fisher-Yates driven by splitmix64, so a recorded seed replays the exact same
+/// draw on any build of the server.
+pub fn shuffle<T>(items: &mut [T], seed: i64) {
+    let mut state = seed as u64;
+    for pos in (1..items.len()).rev() {
+        let swap_with = (splitmix64(&mut state) % ((pos as u64) + 1)) as usize;
+        items.swap(pos, swap_with);
+    }
+}
+
+/// Orders participants by where they finished in `standings`, best first unless
+/// `reverse`. Poets missing from the standings keep their posted order at the end.
+pub fn order_by_standings(
+    participants: Vec<Participant>,
+    standings: &[Standing],
+    reverse: bool
+) -> Vec<Participant> {
+    let mut placed: Vec<(usize, Participant)> = Vec::new();
+    let mut unplaced: Vec<Participant> = Vec::new();
+    for participant in participants {
+        match
+            standings
+                .iter()
+                .position(|standing| standing.participation.participant.id == participant.id)
+        {
+            Some(position) => placed.push((position, participant)),
+            None => unplaced.push(participant),
+        }
+    }
+
+    placed.sort_by_key(|(position, _)| *position);
+    if reverse {
+        placed.reverse();
+    }
+
+    let mut results: Vec<Participant> = placed
+        .into_iter()
+        .map(|(_, participant)| participant)
+        .collect();
+    results.extend(unplaced);
+
+    return results;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ Participation, ParticipationResponse };
+
+    fn participant(id: &str) -> Participant {
+        return Participant {
+            id: id.to_owned(),
+            name: format!("Poet {}", id),
+            pronouns: None,
+            room_id: "room".to_owned(),
+            organization_id: None,
+            competitive: true,
+        };
+    }
+
+    fn standing(id: &str, rank: Option<i32>) -> Standing {
+        let participation = Participation {
+            id: format!("participation-{}", id),
+            performance_notes: None,
+            performance_length_in_seconds: None,
+            deduction: None,
+            score: None,
+            performance_order: 0,
+            round_id: "round".to_owned(),
+            participant_id: id.to_owned(),
+            competitive: true,
+            timer_started_at: None,
+            timer_stopped_at: None,
+        };
+        return Standing {
+            rank,
+            tied: false,
+            participation: ParticipationResponse::new(participation, participant(id)),
+        };
+    }
+
+    fn ids(participants: &[Participant]) -> Vec<&str> {
+        return participants
+            .iter()
+            .map(|participant| participant.id.as_str())
+            .collect();
+    }
+
+    #[test]
+    fn cut_sizes_must_be_positive_and_shrink() {
+        assert!(validate_cut_sizes(&[12, 6, 3]).is_ok());
+        assert!(validate_cut_sizes(&[6, 6]).is_ok());
+        assert!(validate_cut_sizes(&[]).is_ok());
+        assert!(validate_cut_sizes(&[6, 0]).is_err());
+        assert!(validate_cut_sizes(&[6, 8]).is_err());
+    }
+
+    #[test]
+    fn cut_takes_the_top_of_the_standings() {
+        let standings = vec![
+            standing("a", Some(1)),
+            standing("b", Some(2)),
+            standing("c", Some(3)),
+            standing("d", Some(4))
+        ];
+        assert_eq!(ids(&select_cut(&standings, 2).unwrap()), vec!["a", "b"]);
+        assert_eq!(ids(&select_cut(&standings, 10).unwrap()).len(), 4);
+    }
+
+    #[test]
+    fn cut_refuses_an_unscored_round() {
+        let standings = vec![standing("a", Some(1)), standing("b", None)];
+        assert!(select_cut(&standings, 1).is_err());
+    }
+
+    #[test]
+    fn cut_refuses_a_tie_on_the_cut_line() {
+        let standings = vec![
+            standing("a", Some(1)),
+            standing("b", Some(2)),
+            standing("c", Some(2)),
+            standing("d", Some(4))
+        ];
+        assert!(select_cut(&standings, 2).is_err());
+        assert_eq!(ids(&select_cut(&standings, 3).unwrap()), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn shuffle_replays_the_same_draw_for_a_seed() {
+        let mut first: Vec<i32> = (0..20).collect();
+        let mut second: Vec<i32> = (0..20).collect();
+        shuffle(&mut first, 42);
+        shuffle(&mut second, 42);
+        assert_eq!(first, second);
+
+        let mut other: Vec<i32> = (0..20).collect();
+        shuffle(&mut other, 43);
+        assert_ne!(first, other);
+
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..20).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn order_by_standings_puts_unplaced_poets_last() {
+        let standings = vec![standing("b", Some(1)), standing("a", Some(2))];
+        let participants = vec![participant("a"), participant("c"), participant("b")];
+        assert_eq!(
+            ids(&order_by_standings(participants.clone(), &standings, false)),
+            vec!["b", "a", "c"]
+        );
+        assert_eq!(ids(&order_by_standings(participants, &standings, true)), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn unknown_order_strategy_is_rejected() {
+        assert_eq!(OrderStrategy::parse("reverse_standings"), Some(OrderStrategy::ReverseStandings));
+        assert!(validate_order_strategy("alphabetical").is_err());
+    }
+}
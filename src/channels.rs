@@ -0,0 +1,365 @@
+use std::{ collections::{ HashMap, VecDeque }, sync::{ Arc, Mutex }, time::{ Duration, Instant } };
+use serde_json::json;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+use crate::{ error::AppError, models::{ EventMessage, RoomEvent } };
+
+pub const CHANNEL_CAPACITY: usize = 100;
+pub const EVENT_HISTORY_SIZE: usize = 256;
+/// How long a room nobody is listening to keeps its history for reconnects.
+pub const EVENT_HISTORY_IDLE: Duration = Duration::from_secs(30 * 60);
+
+/// `transient` messages (timer ticks, command replies) aren't numbered or kept
+/// in the history; they repeat the seq of the room's latest event.
+#[derive(Debug, Clone)]
+pub struct SequencedMessage {
+    pub seq: u64,
+    pub text: String,
+    pub transient: bool,
+}
+
+/// What a reconnecting client needs to get back in step with the room.
+#[derive(Debug, Clone)]
+pub enum Resync {
+    Missed(Vec<SequencedMessage>),
+    Snapshot,
+}
+
+#[derive(Default)]
+struct RoomChannel {
+    tx: Option<broadcast::Sender<SequencedMessage>>,
+    last_seq: u64,
+    last_sent: Option<Instant>,
+    history: VecDeque<SequencedMessage>,
+}
+
+/// Per-room event stream. Every event gets the room's next sequence number and
+/// is kept in a short history, so clients can reconnect and pick up where they
+/// left off. The broadcast channel only exists while someone is listening.
+/// Sequence numbers start over whenever the server does, so every message also
+/// carries the `epoch` of the process that numbered it.
+pub struct RoomChannels {
+    epoch: String,
+    capacity: usize,
+    history_size: usize,
+    history_idle: Duration,
+    rooms: Mutex<HashMap<String, RoomChannel>>,
+}
+
+impl RoomChannels {
+    pub fn new(capacity: usize, history_size: usize, history_idle: Duration) -> RoomChannels {
+        return RoomChannels {
+            epoch: Uuid::new_v4().to_string(),
+            capacity,
+            history_size,
+            history_idle,
+            rooms: Mutex::new(HashMap::new()),
+        };
+    }
+
+    pub fn epoch(&self) -> &str {
+        return &self.epoch;
+    }
+
+    /// Joins the room's live stream. Also returns the sequence number of the
+    /// last event sent before joining; everything received afterwards is newer.
+    pub fn subscribe(self: &Arc<Self>, room_id: &str) -> (Subscription, u64) {
+        let mut rooms = self.rooms.lock().unwrap();
+        let room = rooms.entry(room_id.to_owned()).or_default();
+        let rx = room.tx.get_or_insert_with(|| broadcast::channel(self.capacity).0).subscribe();
+        let subscription = Subscription {
+            channels: self.clone(),
+            room_id: room_id.to_owned(),
+            rx: Some(rx),
+        };
+        return (subscription, room.last_seq);
+    }
+
+    /// Numbers the event, records it and sends it to everyone listening in the room.
+    pub fn send(&self, room_id: &str, event: RoomEvent) -> u64 {
+        let mut rooms = self.rooms.lock().unwrap();
+        let now = Instant::now();
+        self.forget_idle(&mut rooms, now);
+
+        let room = rooms.entry(room_id.to_owned()).or_default();
+        room.last_seq += 1;
+        room.last_sent = Some(now);
+        let message = SequencedMessage {
+            seq: room.last_seq,
+            text: json!(EventMessage::new(room_id, &self.epoch, room.last_seq, event)).to_string(),
+            transient: false,
+        };
+
+        if room.history.len() == self.history_size {
+            room.history.pop_front();
+        }
+        room.history.push_back(message.clone());
+
+        if let Some(tx) = &room.tx {
+            if tx.send(message).is_err() {
+                room.tx = None;
+            }
+        }
+        return room.last_seq;
+    }
+
+    /// Sends an event only to whoever is listening right now. Reconnecting
+    /// clients never see it again, so it must be safe to miss.
+    pub fn send_transient(&self, room_id: &str, event: RoomEvent) {
+        let rooms = self.rooms.lock().unwrap();
+        let Some(room) = rooms.get(room_id) else {
+            return;
+        };
+        if let Some(tx) = &room.tx {
+            let _ = tx.send(SequencedMessage {
+                seq: room.last_seq,
+                text: json!(EventMessage::new(room_id, &self.epoch, room.last_seq, event)).to_string(),
+                transient: true,
+            });
+        }
+    }
+
+    /// Events after `last_seq`, or a snapshot when the history no longer reaches
+    /// back that far. A `last_seq` numbered under another epoch (or with none
+    /// given) says nothing about this process's history, so it always gets a
+    /// snapshot.
+    pub fn since(&self, room_id: &str, epoch: Option<&str>, last_seq: u64) -> Resync {
+        if last_seq > 0 && epoch != Some(self.epoch.as_str()) {
+            return Resync::Snapshot;
+        }
+        let rooms = self.rooms.lock().unwrap();
+        let Some(room) = rooms.get(room_id) else {
+            return if last_seq == 0 { Resync::Missed(Vec::new()) } else { Resync::Snapshot };
+        };
+        if last_seq > room.last_seq {
+            return Resync::Snapshot;
+        }
+        let oldest = room.history.front().map_or(room.last_seq + 1, |message| message.seq);
+        if last_seq + 1 < oldest {
+            return Resync::Snapshot;
+        }
+        let missed = room.history
+            .iter()
+            .filter(|message| message.seq > last_seq)
+            .cloned()
+            .collect();
+        return Resync::Missed(missed);
+    }
+
+    /// Clears the history of rooms that nobody has listened to or sent to for a
+    /// while. Their `last_seq` stays, so a client that comes back much later is
+    /// sent a snapshot rather than being told it missed nothing.
+    fn forget_idle(&self, rooms: &mut HashMap<String, RoomChannel>, now: Instant) {
+        for room in rooms.values_mut() {
+            let idle = room.last_sent.is_some_and(|last_sent| now - last_sent >= self.history_idle);
+            if room.tx.is_none() && idle && !room.history.is_empty() {
+                room.history = VecDeque::new();
+            }
+        }
+    }
+
+    /// Drops the room's broadcast channel once its last listener has gone. The
+    /// history is kept for reconnects until the room has been idle for a while.
+    fn release(&self, room_id: &str) {
+        let mut rooms = self.rooms.lock().unwrap();
+        if let Some(room) = rooms.get_mut(room_id) {
+            if room.tx.as_ref().is_some_and(|tx| tx.receiver_count() == 0) {
+                room.tx = None;
+            }
+        }
+    }
+
+    pub fn last_seq(&self, room_id: &str) -> u64 {
+        return self.rooms.lock().unwrap().get(room_id).map_or(0, |room| room.last_seq);
+    }
+
+    /// Forgets a deleted room entirely.
+    pub fn remove(&self, room_id: &str) {
+        self.rooms.lock().unwrap().remove(room_id);
+    }
+}
+
+/// A listener in one room. Dropping it releases the room's channel if nobody
+/// else is listening.
+pub struct Subscription {
+    channels: Arc<RoomChannels>,
+    room_id: String,
+    rx: Option<broadcast::Receiver<SequencedMessage>>,
+}
+
+impl Subscription {
+    pub fn room_id(&self) -> &str {
+        return &self.room_id;
+    }
+
+    pub async fn recv(&mut self) -> Result<SequencedMessage, broadcast::error::RecvError> {
+        return match &mut self.rx {
+            Some(rx) => rx.recv().await,
+            None => Err(broadcast::error::RecvError::Closed),
+        };
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        // The receiver has to go first so it no longer counts as a listener.
+        drop(self.rx.take());
+        self.channels.release(&self.room_id);
+    }
+}
+
+pub const MAX_ANNOUNCEMENT_LENGTH: usize = 280;
+
+pub fn validate_announcement(message: &str) -> Result<String, AppError> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err(AppError::validation("message must not be empty"));
+    }
+    if message.chars().count() > MAX_ANNOUNCEMENT_LENGTH {
+        return Err(
+            AppError::validation(format!("message must be at most {} characters", MAX_ANNOUNCEMENT_LENGTH))
+        );
+    }
+    return Ok(message.to_owned());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renamed(name: &str) -> RoomEvent {
+        return RoomEvent::RoomRenamed { name: name.to_owned() };
+    }
+
+    fn seqs(resync: Resync) -> Option<Vec<u64>> {
+        return match resync {
+            Resync::Missed(messages) =>
+                Some(
+                    messages
+                        .iter()
+                        .map(|message| message.seq)
+                        .collect()
+                ),
+            Resync::Snapshot => None,
+        };
+    }
+
+    #[tokio::test]
+    async fn events_only_reach_their_room() {
+        let channels = Arc::new(RoomChannels::new(CHANNEL_CAPACITY, EVENT_HISTORY_SIZE, EVENT_HISTORY_IDLE));
+        let (mut first, _) = channels.subscribe("a");
+        let (mut second, _) = channels.subscribe("b");
+        channels.send("a", renamed("for a"));
+        channels.send("b", renamed("for b"));
+
+        channels.send("a", renamed("again for a"));
+
+        assert!(first.recv().await.unwrap().text.contains("for a"));
+        assert!(second.recv().await.unwrap().text.contains("for b"));
+        assert!(first.recv().await.unwrap().text.contains("again for a"));
+    }
+
+    #[test]
+    fn events_are_numbered_per_room() {
+        let channels = Arc::new(RoomChannels::new(CHANNEL_CAPACITY, EVENT_HISTORY_SIZE, EVENT_HISTORY_IDLE));
+        assert_eq!(channels.send("a", renamed("one")), 1);
+        assert_eq!(channels.send("a", renamed("two")), 2);
+        assert_eq!(channels.send("b", renamed("one")), 1);
+        assert_eq!(channels.last_seq("a"), 2);
+        assert_eq!(channels.last_seq("c"), 0);
+
+        let (_subscription, last_seq) = channels.subscribe("a");
+        assert_eq!(last_seq, 2);
+    }
+
+    #[test]
+    fn reconnect_within_the_history_gets_what_it_missed() {
+        let channels = RoomChannels::new(CHANNEL_CAPACITY, 3, EVENT_HISTORY_IDLE);
+        for pos in 0..5 {
+            channels.send("a", renamed(&pos.to_string()));
+        }
+        assert_eq!(seqs(channels.since("a", Some(channels.epoch()), 2)), Some(vec![3, 4, 5]));
+        assert_eq!(seqs(channels.since("a", Some(channels.epoch()), 4)), Some(vec![5]));
+        assert_eq!(seqs(channels.since("a", Some(channels.epoch()), 5)), Some(vec![]));
+    }
+
+    #[test]
+    fn reconnect_beyond_the_history_gets_a_snapshot() {
+        let channels = RoomChannels::new(CHANNEL_CAPACITY, 3, EVENT_HISTORY_IDLE);
+        for pos in 0..5 {
+            channels.send("a", renamed(&pos.to_string()));
+        }
+        assert_eq!(seqs(channels.since("a", Some(channels.epoch()), 1)), None);
+        assert_eq!(seqs(channels.since("a", Some(channels.epoch()), 6)), None);
+        assert_eq!(seqs(channels.since("b", Some(channels.epoch()), 1)), None);
+        assert_eq!(seqs(channels.since("b", Some(channels.epoch()), 0)), Some(vec![]));
+    }
+
+    #[test]
+    fn dropping_the_last_subscription_releases_the_channel_but_keeps_history() {
+        let channels = Arc::new(RoomChannels::new(CHANNEL_CAPACITY, EVENT_HISTORY_SIZE, EVENT_HISTORY_IDLE));
+        let (first, _) = channels.subscribe("a");
+        let (second, _) = channels.subscribe("a");
+        channels.send("a", renamed("one"));
+
+        drop(first);
+        assert!(channels.rooms.lock().unwrap()["a"].tx.is_some());
+        drop(second);
+        assert!(channels.rooms.lock().unwrap()["a"].tx.is_none());
+
+        assert_eq!(seqs(channels.since("a", Some(channels.epoch()), 0)), Some(vec![1]));
+        channels.remove("a");
+        assert_eq!(channels.last_seq("a"), 0);
+    }
+
+    #[test]
+    fn announcements_are_trimmed_and_bounded() {
+        assert_eq!(validate_announcement("  Break!  ").unwrap(), "Break!");
+        assert!(validate_announcement("   ").is_err());
+        assert!(validate_announcement(&"x".repeat(MAX_ANNOUNCEMENT_LENGTH)).is_ok());
+        assert!(validate_announcement(&"x".repeat(MAX_ANNOUNCEMENT_LENGTH + 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn transient_events_are_neither_numbered_nor_kept() {
+        let channels = Arc::new(RoomChannels::new(CHANNEL_CAPACITY, EVENT_HISTORY_SIZE, EVENT_HISTORY_IDLE));
+        let (mut subscription, _) = channels.subscribe("a");
+        channels.send("a", renamed("one"));
+        channels.send_transient("a", renamed("two"));
+
+        let event = subscription.recv().await.unwrap();
+        assert_eq!((event.seq, event.transient), (1, false));
+        let transient = subscription.recv().await.unwrap();
+        assert_eq!((transient.seq, transient.transient), (1, true));
+        assert_eq!(seqs(channels.since("a", Some(channels.epoch()), 0)), Some(vec![1]));
+    }
+
+    #[test]
+    fn sequence_numbers_from_another_epoch_get_a_snapshot() {
+        let channels = RoomChannels::new(CHANNEL_CAPACITY, EVENT_HISTORY_SIZE, EVENT_HISTORY_IDLE);
+        let restarted = RoomChannels::new(CHANNEL_CAPACITY, EVENT_HISTORY_SIZE, EVENT_HISTORY_IDLE);
+        assert_ne!(channels.epoch(), restarted.epoch());
+        for pos in 0..3 {
+            channels.send("a", renamed(&pos.to_string()));
+        }
+
+        assert_eq!(seqs(channels.since("a", Some(restarted.epoch()), 1)), None);
+        assert_eq!(seqs(channels.since("a", None, 1)), None);
+        assert_eq!(seqs(channels.since("a", None, 0)), Some(vec![1, 2, 3]));
+        assert_eq!(seqs(channels.since("a", Some(channels.epoch()), 1)), Some(vec![2, 3]));
+    }
+
+    #[test]
+    fn idle_rooms_without_listeners_lose_their_history() {
+        let channels = Arc::new(RoomChannels::new(CHANNEL_CAPACITY, EVENT_HISTORY_SIZE, Duration::ZERO));
+        channels.send("a", renamed("one"));
+        let (_subscription, _) = channels.subscribe("b");
+        channels.send("b", renamed("one"));
+
+        channels.send("c", renamed("one"));
+        assert_eq!(seqs(channels.since("a", Some(channels.epoch()), 0)), None);
+        assert_eq!(seqs(channels.since("a", Some(channels.epoch()), 1)), Some(vec![]));
+        assert_eq!(seqs(channels.since("b", Some(channels.epoch()), 0)), Some(vec![1]));
+        assert_eq!(channels.last_seq("a"), 1);
+    }
+}
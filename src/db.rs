@@ -1,19 +1,137 @@
 use chrono::{ DateTime, Utc };
-use diesel::{ prelude::*, pg::PgConnection };
+use diesel::{
+    prelude::*,
+    pg::PgConnection,
+    r2d2::{ ConnectionManager, Pool },
+    result::{ DatabaseErrorKind, Error as DieselError },
+};
 use diesel_migrations::{ embed_migrations, EmbeddedMigrations, MigrationHarness };
-use std::{ time::SystemTime };
+use std::{ collections::HashMap, time::{ Duration, SystemTime } };
 use uuid::Uuid;
-use crate::{models::*, schema::participations::performance_order};
+use crate::{
+    access::*,
+    advancement::*,
+    error::AppError,
+    models::*,
+    schema::participations::performance_order,
+    scoring::*,
+    standings::*,
+    timer::{ elapsed_seconds, parse_timestamp },
+};
 use dotenv::dotenv;
 use std::env;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
-pub fn run_migration() {
-    establish_connection().run_pending_migrations(MIGRATIONS).unwrap();
+pub const DEFAULT_POOL_SIZE: u32 = 10;
+pub const DEFAULT_POOL_TIMEOUT_SECONDS: u64 = 5;
+pub const DEFAULT_POOL_IDLE_TIMEOUT_SECONDS: u64 = 600;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub fn run_migration(pool: &DbPool) {
+    pool.get()
+        .expect("Error connecting to database")
+        .run_pending_migrations(MIGRATIONS)
+        .expect("Error running migrations");
+}
+
+/// Builds the connection pool from `DATABASE_URL`. `DB_POOL_SIZE`,
+/// `DB_POOL_TIMEOUT_SECONDS` and `DB_POOL_IDLE_TIMEOUT_SECONDS` override the defaults.
+pub fn establish_pool() -> DbPool {
+    dotenv().ok();
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let pool_size = env_or("DB_POOL_SIZE", DEFAULT_POOL_SIZE);
+    let timeout_seconds = env_or("DB_POOL_TIMEOUT_SECONDS", DEFAULT_POOL_TIMEOUT_SECONDS);
+    let idle_timeout_seconds = env_or("DB_POOL_IDLE_TIMEOUT_SECONDS", DEFAULT_POOL_IDLE_TIMEOUT_SECONDS);
+
+    return Pool::builder()
+        .max_size(pool_size)
+        .connection_timeout(Duration::from_secs(timeout_seconds))
+        .idle_timeout(Some(Duration::from_secs(idle_timeout_seconds)))
+        .build(ConnectionManager::<PgConnection>::new(&database_url))
+        .unwrap_or_else(|error| panic!("Error connecting to {}: {}", database_url, error));
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    return env
+        ::var(key)
+        .ok()
+        .map(|value| value.parse().unwrap_or_else(|_| panic!("{} must be a number", key)))
+        .unwrap_or(default);
+}
+
+/// Checks a connection out of the pool and runs `query` on the blocking thread
+/// pool, so Diesel's synchronous calls never hold up the async runtime.
+pub async fn with_connection<T, F>(pool: &DbPool, query: F) -> Result<T, AppError>
+    where T: Send + 'static, F: FnOnce(&mut PgConnection) -> Result<T, AppError> + Send + 'static
+{
+    let pool = pool.clone();
+    return tokio::task
+        ::spawn_blocking(move || {
+            let conn = &mut pool.get()?;
+            query(conn)
+        }).await
+        .map_err(|error| AppError::Internal(error.to_string()))?;
+}
+
+fn find_room(conn: &mut PgConnection, room_id_parameter: &str) -> Result<Room, AppError> {
+    return crate::schema::rooms::table
+        .find(room_id_parameter)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| not_found("room", room_id_parameter));
+}
+
+fn find_round(conn: &mut PgConnection, round_id_parameter: &str) -> Result<Round, AppError> {
+    return crate::schema::rounds::table
+        .find(round_id_parameter)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| not_found("round", round_id_parameter));
+}
+
+fn find_participation(
+    conn: &mut PgConnection,
+    participation_id_parameter: &str
+) -> Result<Participation, AppError> {
+    return crate::schema::participations::table
+        .find(participation_id_parameter)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| not_found("participation", participation_id_parameter));
+}
+
+fn lock_participation(
+    conn: &mut PgConnection,
+    participation_id_parameter: &str
+) -> Result<Participation, AppError> {
+    return crate::schema::participations::table
+        .find(participation_id_parameter)
+        .for_update()
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| not_found("participation", participation_id_parameter));
+}
+
+/// Lookups, updates and deletes that match no row were aimed at an id that
+/// doesn't exist.
+fn not_found(kind: &str, id_value: &str) -> AppError {
+    return AppError::NotFound(format!("No {} with id {}", kind, id_value));
 }
 
-pub fn insert_room(conn: &mut PgConnection, name_value: &str) -> Room {
+pub fn insert_room(
+    conn: &mut PgConnection,
+    name_value: &str,
+    policy: &ScoringPolicy,
+    tie_break_method_value: TieBreakMethod,
+    cut_sizes_value: Vec<i32>,
+    range: &ScoreRange,
+    creator: &OrganizationMember
+) -> Result<Room, AppError> {
+    let new_join_code = unused_join_code(conn)?;
+
     use crate::schema::rooms::dsl::*;
     let new_room = Room {
         id: Uuid::new_v4().to_string(),
@@ -21,372 +139,3574 @@ pub fn insert_room(conn: &mut PgConnection, name_value: &str) -> Room {
         created: iso_date(),
         round_id_current: None,
         participation_id_current: None,
+        judge_count: policy.judge_count,
+        drop_count: policy.drop_count,
+        aggregation_mode: policy.aggregation_mode.as_str().to_owned(),
+        tie_break_method: tie_break_method_value.as_str().to_owned(),
+        cut_sizes: cut_sizes_value,
+        score_min: range.min,
+        score_max: range.max,
+        score_precision: range.precision,
+        join_code: new_join_code,
+        organizer_id: Some(creator.organizer_id.clone()),
+        organization_id: Some(creator.organization_id.clone()),
     };
-    diesel::insert_into(rooms).values(&new_room).execute(conn).expect("Error inserting room");
-    return new_room;
+    diesel::insert_into(rooms).values(&new_room).execute(conn)?;
+    return Ok(new_room);
 }
 
-pub fn insert_participant(
-    conn: &mut PgConnection,
-    name_value: &str,
-    pronouns_value: Option<String>,
-    room_id_value: &str
-) -> Participant {
-    use crate::schema::participants::dsl::*;
-    let new_participant = Participant {
-        id: Uuid::new_v4().to_string(),
-        name: name_value.to_owned(),
-        pronouns: pronouns_value,
-        room_id: room_id_value.to_string(),
-    };
-    let existing_participant: Option<Participant> = participants
-        .filter(name.eq(name_value).and(room_id.eq(room_id_value)))
-        .first(conn)
-        .optional()
-        .unwrap();
-    if existing_participant.is_some() {
-        panic!("Existing participant");
-    }
-    {
-        use crate::schema::rooms::dsl::*;
-        let matching_room: Option<Room> = rooms
-            .filter(id.eq(room_id_value))
+/// Works out who a bearer token speaks for, whatever room it is for. Signed
+/// judge tokens are checked against the judge's seat and the room's current
+/// join code; anything else must be an issued credential.
+pub fn authenticate(conn: &mut PgConnection, access_token: &AccessToken) -> Result<Caller, AppError> {
+    let token = access_token
+        .value()
+        .ok_or_else(|| AppError::Unauthorized("an access token is required".to_owned()))?;
+
+    if is_judge_token(token) {
+        let claims = access_token.judge_tokens().verify(token)?;
+        let judge: Judge = crate::schema::judges::table
+            .find(&claims.judge_id)
             .first(conn)
-            .optional()
-            .unwrap();
-        if matching_room.is_none() {
-            panic!("No matching room id");
+            .optional()?
+            .ok_or_else(|| AppError::Unauthorized("judge token is for a seat that has been removed".to_owned()))?;
+        let room = find_room(conn, &judge.room_id)?;
+        if room.join_code != claims.join_code {
+            return Err(
+                AppError::Unauthorized("the room's join code has changed; join the room again".to_owned())
+            );
+        }
+        if judge.token_nonce.as_deref() != Some(claims.nonce.as_str()) {
+            return Err(AppError::Unauthorized("this seat has been released; join the room again".to_owned()));
         }
+        return Ok(Caller {
+            room_id: room.id,
+            role: Role::Judge,
+            judge_id: Some(judge.id),
+            organization_id: room.organization_id,
+        });
     }
 
-    diesel
-        ::insert_into(participants)
-        .values(&new_participant)
-        .execute(conn)
-        .expect("Error inserting participant");
-    return new_participant;
+    use crate::schema::room_credentials::dsl::*;
+    let (credential, tenant): (RoomCredential, Option<String>) = room_credentials
+        .inner_join(crate::schema::rooms::table)
+        .filter(token_hash.eq(hash_token(token)))
+        .select((RoomCredential::as_select(), crate::schema::rooms::organization_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| AppError::Unauthorized("access token is invalid or has been revoked".to_owned()))?;
+    return Ok(Caller {
+        role: credential.role(),
+        room_id: credential.room_id,
+        judge_id: credential.judge_id,
+        organization_id: tenant,
+    });
 }
 
-pub fn update_room(conn: &mut PgConnection, id_value: String, name_value: Option<String>) -> usize {
-    use crate::schema::rooms::dsl::*;
-    let result = diesel
-        ::update(rooms.filter(id.eq(id_value)))
-        .set(
-            &(RoomUpdate {
-                name: name_value,
-            })
-        )
-        .execute(conn)
-        .expect("unable to update room");
-    return result;
+/// Authenticates `token` and checks it may act on the room as one of `roles`.
+/// Organizers act in a room with the role their membership in the room's
+/// organization gives them.
+pub fn authorize(
+    conn: &mut PgConnection,
+    access_token: &AccessToken,
+    room_id_parameter: &str,
+    roles: &[Role]
+) -> Result<Caller, AppError> {
+    let caller = match access_token.value() {
+        Some(token) if is_session_token(token) => {
+            let organizer = authenticate_organizer(conn, Some(token))?;
+            let tenant: Option<String> = crate::schema::rooms::table
+                .find(room_id_parameter)
+                .select(crate::schema::rooms::organization_id)
+                .first::<Option<String>>(conn)
+                .optional()?
+                .flatten();
+            let member = match &tenant {
+                Some(tenant) => find_member(conn, tenant, &organizer.id)?,
+                None => None,
+            };
+            let member = member.ok_or_else(||
+                AppError::Forbidden(format!("room {} is not run by an organization you belong to", room_id_parameter))
+            )?;
+            Caller {
+                room_id: room_id_parameter.to_owned(),
+                role: member.role().room_role(),
+                judge_id: None,
+                organization_id: tenant,
+            }
+        }
+        _ => authenticate(conn, access_token)?,
+    };
+    check_access(&caller, room_id_parameter, roles)?;
+    return Ok(caller);
 }
 
-pub fn update_participant(
-    conn: &mut PgConnection,
-    id_value: String,
-    name_value: Option<String>,
-    pronouns_value: Option<String>
-) -> usize {
-    use crate::schema::participants::dsl::*;
-    let result = diesel
-        ::update(participants.filter(id.eq(id_value)))
-        .set(
-            &(ParticipantUpdate {
-                name: name_value,
-                pronouns: pronouns_value,
-            })
-        )
-        .execute(conn)
-        .expect("unable to update participant");
-    return result;
+/// Checks that the token is valid, without tying it to any room.
+fn authenticate_any(conn: &mut PgConnection, access_token: &AccessToken) -> Result<(), AppError> {
+    match access_token.value() {
+        Some(token) if is_session_token(token) => {
+            authenticate_organizer(conn, Some(token))?;
+        }
+        _ => {
+            authenticate(conn, access_token)?;
+        }
+    }
+    return Ok(());
 }
 
-pub fn update_participation(
+/// Authorizes a request about something that lives in a room, such as a round
+/// or a judge seat. The token is checked before anything is said about the id,
+/// and a caller who can't see the room gets the same `NotFound` as for an id
+/// that doesn't exist, so ids can't be probed from outside the room.
+fn authorize_found<T>(
     conn: &mut PgConnection,
-    id_value: String,
-    notes_value: Option<String>,
-    length_value: Option<i32>
-) -> usize {
-    use crate::schema::participations::dsl::*;
-    let mut deduction_value = None;
-    if let Some(length) = length_value {
-        if length > 190 {
-            deduction_value = Some((((length as f32) - 190_f32) / 10_f32).floor() * 0.5);
+    access_token: &AccessToken,
+    kind: &str,
+    id_value: &str,
+    found: Result<T, AppError>,
+    room_id_of: impl Fn(&T) -> &str,
+    roles: &[Role]
+) -> Result<(T, Caller), AppError> {
+    let item = match found {
+        Ok(item) => item,
+        Err(AppError::NotFound(_)) => {
+            authenticate_any(conn, access_token)?;
+            return Err(not_found(kind, id_value));
         }
-    }
+        Err(error) => {
+            return Err(error);
+        }
+    };
+    let room_id_value = room_id_of(&item).to_owned();
+    let caller = match authorize(conn, access_token, &room_id_value, EVERYONE) {
+        Err(AppError::Forbidden(_)) => {
+            return Err(not_found(kind, id_value));
+        }
+        caller => caller?,
+    };
+    check_access(&caller, &room_id_value, roles)?;
+    return Ok((item, caller));
+}
 
-    let result = diesel
-        ::update(participations.filter(id.eq(id_value)))
-        .set(
-            &(ParticipationUpdate {
-                performance_length_in_seconds: length_value,
-                performance_notes: notes_value,
-                deduction: deduction_value,
-                score: None,
-            })
-        )
-        .execute(conn)
-        .expect("unable to update participation");
-    return result;
+/// The room a round belongs to, once the caller may act on it as one of `roles`.
+pub fn authorize_round(
+    conn: &mut PgConnection,
+    access_token: &AccessToken,
+    round_id_parameter: &str,
+    roles: &[Role]
+) -> Result<(Room, Caller), AppError> {
+    let found = retrieve_round_room(conn, round_id_parameter);
+    return authorize_found(conn, access_token, "round", round_id_parameter, found, |room| &room.id, roles);
 }
 
-pub fn remove_room(conn: &mut PgConnection, id_value: String) -> usize {
-    use crate::schema::rooms::dsl::*;
+/// The room a participation belongs to, once the caller may act on it as one of `roles`.
+pub fn authorize_participation(
+    conn: &mut PgConnection,
+    access_token: &AccessToken,
+    participation_id_parameter: &str,
+    roles: &[Role]
+) -> Result<(Room, Caller), AppError> {
+    let found = retrieve_participation_room(conn, participation_id_parameter);
+    return authorize_found(
+        conn,
+        access_token,
+        "participation",
+        participation_id_parameter,
+        found,
+        |room| &room.id,
+        roles
+    );
+}
 
-    let result = diesel
-        ::delete(rooms.filter(id.eq(id_value)))
-        .execute(conn)
-        .expect("unable to delete room");
-    
-    return result;
+pub fn authorize_participant(
+    conn: &mut PgConnection,
+    access_token: &AccessToken,
+    participant_id_parameter: &str,
+    roles: &[Role]
+) -> Result<(Participant, Caller), AppError> {
+    let found = retrieve_participant(conn, participant_id_parameter);
+    return authorize_found(
+        conn,
+        access_token,
+        "participant",
+        participant_id_parameter,
+        found,
+        |participant| &participant.room_id,
+        roles
+    );
 }
 
-pub fn remove_participant(conn: &mut PgConnection, id_value: String) -> usize {
-    use crate::schema::participants::dsl::*;
-    let result = diesel
-        ::delete(participants.filter(id.eq(id_value)))
-        .execute(conn)
-        .expect("unable to delete participant");
-    return result;
+pub fn authorize_judge(
+    conn: &mut PgConnection,
+    access_token: &AccessToken,
+    judge_id_parameter: &str,
+    roles: &[Role]
+) -> Result<(Judge, Caller), AppError> {
+    let found = retrieve_judge(conn, judge_id_parameter);
+    return authorize_found(conn, access_token, "judge", judge_id_parameter, found, |judge| &judge.room_id, roles);
 }
 
-pub fn insert_score(
+/// Works out which organizer a session token belongs to.
+pub fn authenticate_organizer(conn: &mut PgConnection, token: Option<&str>) -> Result<Organizer, AppError> {
+    let token = token.ok_or_else(|| AppError::Unauthorized("sign in as an organizer first".to_owned()))?;
+    if !is_session_token(token) {
+        return Err(AppError::Unauthorized("this needs an organizer session, not a room credential".to_owned()));
+    }
+
+    use crate::schema::organizer_sessions::dsl::*;
+    let session: OrganizerSession = organizer_sessions
+        .filter(token_hash.eq(hash_token(token)))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| AppError::Unauthorized("session is invalid or has been signed out".to_owned()))?;
+    if parse_timestamp(&session.expires_at)? < Utc::now() {
+        return Err(AppError::Unauthorized("session has expired; sign in again".to_owned()));
+    }
+    return Ok(crate::schema::organizers::table.find(&session.organizer_id).first(conn)?);
+}
+
+pub fn insert_organizer(
     conn: &mut PgConnection,
-    value_value: &f32,
-    participation_id_value: &str,
-    submitter_id_value: &str
-) -> Score {
-    use crate::schema::scores::dsl::*;
-    let new_score = Score {
+    email_value: &str,
+    name_value: &str,
+    password_hash_value: String
+) -> Result<Organizer, AppError> {
+    use crate::schema::organizers::dsl::*;
+    let taken: i64 = organizers.filter(email.eq(email_value)).count().get_result(conn)?;
+    if taken > 0 {
+        return Err(AppError::Conflict(format!("an organizer with email {} already exists", email_value)));
+    }
+
+    let new_organizer = Organizer {
         id: Uuid::new_v4().to_string(),
-        value: value_value.to_owned(),
-        submitter_id: Some(submitter_id_value.to_string()),
-        participation_id: participation_id_value.to_string(),
+        email: email_value.to_owned(),
+        name: name_value.to_owned(),
+        password_hash: password_hash_value,
+        created: iso_date(),
     };
+    diesel::insert_into(organizers).values(&new_organizer).execute(conn)?;
+    return Ok(new_organizer);
+}
 
-    let existing_score: Option<Score> = scores.filter(submitter_id.eq(submitter_id_value)).filter(participation_id.eq(participation_id_value)).first(conn).optional().unwrap();
-
-    match existing_score {
-        None => diesel::insert_into(scores).values(&new_score).execute(conn).expect("Error inserting score"),
-        Some(score) => diesel::update(scores).filter(id.eq(score.id)).set(value.eq(value_value)).execute(conn).expect("Error updating score"),
+/// Starts a new session for the organizer.
+pub fn insert_session(conn: &mut PgConnection, organizer: Organizer) -> Result<SessionResponse, AppError> {
+    use crate::schema::organizer_sessions::dsl::*;
+    let token = generate_session_token();
+    let expiry = session_expiry().to_rfc3339();
+    let new_session = OrganizerSession {
+        id: Uuid::new_v4().to_string(),
+        organizer_id: organizer.id.clone(),
+        token_hash: hash_token(&token),
+        created: iso_date(),
+        expires_at: expiry.clone(),
     };
+    diesel::insert_into(organizer_sessions).values(&new_session).execute(conn)?;
+    return Ok(SessionResponse { token, expires_at: expiry, organizer });
+}
 
-    
-    let _scores: Vec<Score> = scores
-        .filter(participation_id.eq(participation_id_value))
-        .order(value.asc())
-        .load::<Score>(conn)
-        .expect("Error aggregating scores");
-
-    if _scores.len() == 5 {
-        let mut aggregate_score = 0_f32;
-        for (pos, score_iter) in _scores.iter().enumerate() {
-            if pos != 0 && pos != 4 {
-                aggregate_score += score_iter.value;
-            }
+/// Checks an organizer's password and starts a session. Unknown emails and
+/// wrong passwords get the same answer.
+pub fn sign_in(conn: &mut PgConnection, email_value: &str, password: &str) -> Result<SessionResponse, AppError> {
+    let organizer: Option<Organizer> = crate::schema::organizers::table
+        .filter(crate::schema::organizers::email.eq(email_value))
+        .first(conn)
+        .optional()?;
+    match organizer {
+        Some(organizer) if verify_password(password, &organizer.password_hash) => {
+            return insert_session(conn, organizer);
+        }
+        _ => {
+            return Err(AppError::Unauthorized("email or password is incorrect".to_owned()));
         }
-
-        use crate::schema::participations::dsl::*;
-        diesel
-            ::update(participations.filter(id.eq(participation_id_value)))
-            .set(
-                &(ParticipationUpdate {
-                    performance_length_in_seconds: None,
-                    performance_notes: None,
-                    deduction: None,
-                    score: Some(aggregate_score),
-                })
-            )
-            .execute(conn)
-            .expect("unable to update participation");
     }
+}
 
-    return new_score;
+/// Ends the session the token belongs to, expired or not.
+pub fn remove_session(conn: &mut PgConnection, token: Option<&str>) -> Result<(), AppError> {
+    let token = token
+        .filter(|token| is_session_token(token))
+        .ok_or_else(|| AppError::Unauthorized("sign in as an organizer first".to_owned()))?;
+
+    use crate::schema::organizer_sessions::dsl::*;
+    let deleted = diesel::delete(organizer_sessions.filter(token_hash.eq(hash_token(token)))).execute(conn)?;
+    if deleted == 0 {
+        return Err(AppError::Unauthorized("session is invalid or has been signed out".to_owned()));
+    }
+    return Ok(());
 }
 
-pub fn retrieve_rooms(conn: &mut PgConnection) -> Vec<Room> {
-    use crate::schema::rooms::dsl::*;
-    let results = rooms
-        .order(created.desc())
-        .limit(10)
-        .load::<Room>(conn)
-        .expect("Error loading rooms");
+fn find_member(
+    conn: &mut PgConnection,
+    organization_id_value: &str,
+    organizer_id_value: &str
+) -> Result<Option<OrganizationMember>, AppError> {
+    use crate::schema::organization_members::dsl::*;
+    let result = organization_members
+        .filter(organization_id.eq(organization_id_value))
+        .filter(organizer_id.eq(organizer_id_value))
+        .first(conn)
+        .optional()?;
+    return Ok(result);
+}
 
-    return results;
+/// Checks the session's organizer is a member of the organization with one of `roles`.
+pub fn authorize_member(
+    conn: &mut PgConnection,
+    token: Option<&str>,
+    organization_id_value: &str,
+    roles: &[MemberRole]
+) -> Result<OrganizationMember, AppError> {
+    let organizer = authenticate_organizer(conn, token)?;
+    let member = find_member(conn, organization_id_value, &organizer.id)?.ok_or_else(||
+        AppError::Forbidden(format!("you are not a member of organization {}", organization_id_value))
+    )?;
+    if !roles.contains(&member.role()) {
+        return Err(
+            AppError::Forbidden(format!("the {} role is not allowed to do this", member.role().as_str()))
+        );
+    }
+    return Ok(member);
 }
 
-pub fn retrieve_room(conn: &mut PgConnection, room_id_parameter: &str) -> RoomResponse {
-    use crate::schema::rooms::dsl::*;
-    let room_results: Room = rooms.find(room_id_parameter).first(conn).expect("Error loading room");
+pub fn authorize_credential(
+    conn: &mut PgConnection,
+    access_token: &AccessToken,
+    credential_id_parameter: &str,
+    roles: &[Role]
+) -> Result<(RoomCredential, Caller), AppError> {
+    let found = retrieve_credential(conn, credential_id_parameter);
+    return authorize_found(
+        conn,
+        access_token,
+        "credential",
+        credential_id_parameter,
+        found,
+        |credential| &credential.room_id,
+        roles
+    );
+}
 
-    use crate::schema::participants::dsl::*;
-    let match_value = room_id_parameter.to_owned();
-    let participant_results = participants
-        .filter(crate::schema::participants::dsl::room_id.eq(match_value.clone()))
-        .load::<Participant>(conn)
-        .expect("Error loading participants");
+/// A membership and the caller's own membership in the same organization, once
+/// the caller holds one of `roles` there. Like `authorize_found`, memberships
+/// of organizations the caller isn't in are reported as not found.
+pub fn authorize_membership(
+    conn: &mut PgConnection,
+    token: Option<&str>,
+    member_id_value: &str,
+    roles: &[MemberRole]
+) -> Result<(OrganizationMember, OrganizationMember), AppError> {
+    let organizer = authenticate_organizer(conn, token)?;
+    let member = retrieve_member(conn, member_id_value)?;
+    let caller = find_member(conn, &member.organization_id, &organizer.id)?.ok_or_else(||
+        not_found("member", member_id_value)
+    )?;
+    if !roles.contains(&caller.role()) {
+        return Err(
+            AppError::Forbidden(format!("the {} role is not allowed to do this", caller.role().as_str()))
+        );
+    }
+    return Ok((member, caller));
+}
 
-    use crate::schema::rounds::dsl::*;
-    let round_results = rounds
-        .filter(crate::schema::rounds::dsl::room_id.eq(match_value))
-        .load::<Round>(conn)
-        .expect("Error loading rounds");
+/// Creates an organization with the organizer as its first owner.
+pub fn insert_organization(
+    conn: &mut PgConnection,
+    name_value: &str,
+    organizer_id_value: &str
+) -> Result<OrganizationResponse, AppError> {
+    return conn.transaction(|conn| {
+        let new_organization = Organization {
+            id: Uuid::new_v4().to_string(),
+            name: name_value.to_owned(),
+            created: iso_date(),
+        };
+        diesel::insert_into(crate::schema::organizations::table).values(&new_organization).execute(conn)?;
 
-    let results = RoomResponse {
-        room: room_results,
-        participants: participant_results,
-        rounds: round_results,
-    };
+        let owner = OrganizationMember {
+            id: Uuid::new_v4().to_string(),
+            organization_id: new_organization.id.clone(),
+            organizer_id: organizer_id_value.to_owned(),
+            role: MemberRole::Owner.as_str().to_owned(),
+            created: iso_date(),
+        };
+        diesel::insert_into(crate::schema::organization_members::table).values(&owner).execute(conn)?;
 
-    return results;
+        Ok(OrganizationResponse { organization: new_organization, role: owner.role })
+    });
 }
 
-pub fn retrieve_round(conn: &mut PgConnection, round_id_parameter: &str) -> RoundResponse {
-    use crate::schema::rounds::dsl::*;
-    let round_results: Round = rounds
-        .find(round_id_parameter)
-        .first(conn)
-        .expect("Error loading round");
+/// Every organization the organizer belongs to, with their role in it.
+pub fn retrieve_organizations(
+    conn: &mut PgConnection,
+    organizer_id_value: &str
+) -> Result<Vec<OrganizationResponse>, AppError> {
+    use crate::schema::{ organization_members, organizations };
+    let results = organizations::table
+        .inner_join(organization_members::table)
+        .filter(organization_members::organizer_id.eq(organizer_id_value))
+        .order(organizations::created.asc())
+        .select((Organization::as_select(), organization_members::role))
+        .load::<(Organization, String)>(conn)?
+        .into_iter()
+        .map(|(organization, role)| OrganizationResponse { organization, role })
+        .collect();
+    return Ok(results);
+}
 
-    use crate::schema::participants::dsl::*;
+pub fn retrieve_organization(
+    conn: &mut PgConnection,
+    member: &OrganizationMember
+) -> Result<OrganizationResponse, AppError> {
+    let organization = crate::schema::organizations::table
+        .find(&member.organization_id)
+        .first::<Organization>(conn)?;
+    return Ok(OrganizationResponse { organization, role: member.role.clone() });
+}
 
-    let participation_results: Vec<(Participation, Participant)> = Participation::belonging_to(
-        &round_results
-    )
-        .order(performance_order.asc())
-        .inner_join(participants)
-        .select((Participation::as_select(), Participant::as_select()))
-        .load::<(Participation, Participant)>(conn)
-        .expect("Error loading participations");
+pub fn update_organization(
+    conn: &mut PgConnection,
+    id_value: &str,
+    name_value: &str
+) -> Result<Organization, AppError> {
+    use crate::schema::organizations::dsl::*;
+    let result = diesel
+        ::update(organizations.find(id_value))
+        .set(name.eq(name_value))
+        .get_result::<Organization>(conn)
+        .optional()?
+        .ok_or_else(|| not_found("organization", id_value))?;
+    return Ok(result);
+}
 
-    let transformed_participation_results = participation_results
+pub fn retrieve_members(
+    conn: &mut PgConnection,
+    organization_id_value: &str
+) -> Result<Vec<MemberResponse>, AppError> {
+    use crate::schema::{ organization_members, organizers };
+    let results = organization_members::table
+        .inner_join(organizers::table)
+        .filter(organization_members::organization_id.eq(organization_id_value))
+        .order(organization_members::created.asc())
+        .select((OrganizationMember::as_select(), Organizer::as_select()))
+        .load::<(OrganizationMember, Organizer)>(conn)?
         .into_iter()
-        .map(|(participation, participant)| ParticipationResponse { participation, participant })
+        .map(|(member, organizer)| MemberResponse { member, organizer })
         .collect();
+    return Ok(results);
+}
 
-    let results = RoundResponse {
-        round: round_results,
-        participations: transformed_participation_results,
+pub fn retrieve_member(conn: &mut PgConnection, id_value: &str) -> Result<OrganizationMember, AppError> {
+    return crate::schema::organization_members::table
+        .find(id_value)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| not_found("member", id_value));
+}
+
+/// Adds an existing organizer account to the organization by email.
+pub fn insert_member(
+    conn: &mut PgConnection,
+    organization_id_value: &str,
+    email_value: &str,
+    role_value: MemberRole
+) -> Result<MemberResponse, AppError> {
+    let organizer: Organizer = crate::schema::organizers::table
+        .filter(crate::schema::organizers::email.eq(email_value))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("No organizer with email {}", email_value)))?;
+    if find_member(conn, organization_id_value, &organizer.id)?.is_some() {
+        return Err(AppError::Conflict(format!("{} is already a member of this organization", email_value)));
+    }
+
+    let new_member = OrganizationMember {
+        id: Uuid::new_v4().to_string(),
+        organization_id: organization_id_value.to_owned(),
+        organizer_id: organizer.id.clone(),
+        role: role_value.as_str().to_owned(),
+        created: iso_date(),
     };
+    diesel::insert_into(crate::schema::organization_members::table).values(&new_member).execute(conn)?;
+    return Ok(MemberResponse { member: new_member, organizer });
+}
 
-    return results;
+/// Locks the organization's memberships and refuses a change that would leave
+/// it with no owner.
+fn keep_an_owner(conn: &mut PgConnection, member: &OrganizationMember) -> Result<(), AppError> {
+    use crate::schema::organization_members::dsl::*;
+    let owners: Vec<String> = organization_members
+        .filter(organization_id.eq(&member.organization_id))
+        .filter(role.eq(MemberRole::Owner.as_str()))
+        .select(id)
+        .for_update()
+        .load(conn)?;
+    if owners.len() <= 1 && owners.contains(&member.id) {
+        return Err(AppError::Conflict("an organization must keep at least one owner".to_owned()));
+    }
+    return Ok(());
 }
 
-pub fn create_next_round(
+pub fn update_member_role(
     conn: &mut PgConnection,
-    room_id_parameter: &str,
-    participants: Vec<Participant>
-) -> Round {
-    use crate::schema::rounds::dsl::*;
+    id_value: &str,
+    role_value: MemberRole
+) -> Result<OrganizationMember, AppError> {
+    return conn.transaction(|conn| {
+        let member = retrieve_member(conn, id_value)?;
+        if role_value != MemberRole::Owner {
+            keep_an_owner(conn, &member)?;
+        }
 
-    let previous_round_response: Option<Round> = rounds
-        .filter(room_id.eq(room_id_parameter))
-        .order(round_number.desc())
-        .first(conn)
-        .optional()
-        .unwrap();
+        use crate::schema::organization_members::dsl::*;
+        let result = diesel
+            ::update(organization_members.find(id_value))
+            .set(role.eq(role_value.as_str()))
+            .get_result::<OrganizationMember>(conn)?;
+        Ok(result)
+    });
+}
 
-    let mut new_round_number = 1;
+pub fn remove_member(conn: &mut PgConnection, id_value: &str) -> Result<OrganizationMember, AppError> {
+    return conn.transaction(|conn| {
+        let member = retrieve_member(conn, id_value)?;
+        keep_an_owner(conn, &member)?;
+        diesel::delete(crate::schema::organization_members::table.find(id_value)).execute(conn)?;
+        Ok(member)
+    });
+}
 
-    if let Some(previous_round) = previous_round_response {
-        let prev_num = previous_round.round_number;
-        new_round_number += prev_num;
+/// The membership a new room is created under: in the organization asked for,
+/// or else the only one the organizer can create rooms in.
+pub fn resolve_room_membership(
+    conn: &mut PgConnection,
+    organizer: &Organizer,
+    requested: Option<&str>
+) -> Result<OrganizationMember, AppError> {
+    if let Some(requested) = requested {
+        let member = find_member(conn, requested, &organizer.id)?.ok_or_else(||
+            AppError::Forbidden(format!("you are not a member of organization {}", requested))
+        )?;
+        if !ORG_ORGANIZERS.contains(&member.role()) {
+            return Err(
+                AppError::Forbidden(format!("the {} role is not allowed to do this", member.role().as_str()))
+            );
+        }
+        return Ok(member);
     }
 
-    let new_round = Round {
-        id: Uuid::new_v4().to_string(),
-        room_id: room_id_parameter.to_owned(),
-        round_number: new_round_number,
+    use crate::schema::organization_members::dsl::*;
+    let mut candidates: Vec<OrganizationMember> = organization_members
+        .filter(organizer_id.eq(&organizer.id))
+        .filter(role.eq_any(ORG_ORGANIZERS.iter().map(|member_role| member_role.as_str())))
+        .load(conn)?;
+    return match candidates.len() {
+        1 => Ok(candidates.remove(0)),
+        0 => Err(AppError::validation("create or join an organization before creating rooms")),
+        _ => Err(AppError::validation("organization_id is required when you belong to several organizations")),
     };
-    diesel::insert_into(rounds).values(&new_round).execute(conn).expect("Error inserting round");
+}
 
+/// A join code no other room is using. Codes are random, so this almost never
+/// takes more than one try.
+fn unused_join_code(conn: &mut PgConnection) -> Result<String, AppError> {
     use crate::schema::rooms::dsl::*;
-    diesel
-        ::update(rooms)
-        .filter(crate::schema::rooms::id.eq(room_id_parameter))
-        .set(round_id_current.eq(&new_round.id))
-        .execute(conn)
-        .expect("Error updating room");
-
-    let mut vec: Vec<Participation> = Vec::new();
-    let parameter_round_id = &new_round.id;
+    for _ in 0..10 {
+        let candidate = generate_join_code();
+        let taken: i64 = rooms.filter(join_code.eq(&candidate)).count().get_result(conn)?;
+        if taken == 0 {
+            return Ok(candidate);
+        }
+    }
+    return Err(AppError::Internal("could not find an unused join code".to_owned()));
+}
 
-    for (pos, participant) in participants.iter().enumerate() {
-        let parameter_participant_id = &participant.id;
+pub fn retrieve_room_by_join_code(conn: &mut PgConnection, code: &str) -> Result<Room, AppError> {
+    let code = normalize_join_code(code);
+    return crate::schema::rooms::table
+        .filter(crate::schema::rooms::join_code.eq(&code))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("No room with join code {}", code)));
+}
 
-        vec.push(Participation {
-            id: Uuid::new_v4().to_string(),
-            participant_id: parameter_participant_id.to_string(),
-            performance_order: pos as i32,
-            round_id: parameter_round_id.to_string(),
-            deduction: None,
-            performance_length_in_seconds: None,
-            performance_notes: None,
-            score: None,
-        });
-    }
+/// Gives a new join code to the room. Judge tokens issued under the old one
+/// stop working, so every seat is released for judges to join again.
+pub fn update_join_code(conn: &mut PgConnection, room_id_parameter: &str) -> Result<Room, AppError> {
+    let new_code = unused_join_code(conn)?;
 
-    use crate::schema::participations::dsl::*;
+    return conn.transaction(|conn| {
+        use crate::schema::rooms::dsl::*;
+        let result = diesel
+            ::update(rooms.find(room_id_parameter))
+            .set(join_code.eq(new_code))
+            .get_result::<Room>(conn)
+            .optional()?
+            .ok_or_else(|| not_found("room", room_id_parameter))?;
 
-    diesel
-        ::insert_into(participations)
-        .values(vec)
-        .execute(conn)
-        .expect("Error inserting participations");
+        use crate::schema::judges;
+        diesel
+            ::update(judges::table.filter(judges::room_id.eq(room_id_parameter)))
+            .set((judges::claimed_at.eq(None::<String>), judges::token_nonce.eq(None::<String>)))
+            .execute(conn)?;
+        Ok(result)
+    });
+}
 
-    return new_round;
+/// Frees a claimed seat and revokes the token issued for it.
+pub fn release_judge_seat(conn: &mut PgConnection, judge_id_parameter: &str) -> Result<Judge, AppError> {
+    use crate::schema::judges::dsl::*;
+    let result = diesel
+        ::update(judges.find(judge_id_parameter))
+        .set((claimed_at.eq(None::<String>), token_nonce.eq(None::<String>)))
+        .get_result::<Judge>(conn)
+        .optional()?
+        .ok_or_else(|| not_found("judge", judge_id_parameter))?;
+    return Ok(result);
 }
 
-pub fn retrieve_participants(
+/// Exchanges a room's join code and a seat for a signed token that scores as
+/// the judge in that seat. Each seat can be claimed once; to hand it to
+/// someone else the host releases it first.
+pub fn issue_judge_token(
     conn: &mut PgConnection,
-    room_id_parameter: &Option<String>
-) -> Vec<Participant> {
-    use crate::schema::participants::dsl::*;
-
-    let mut query = participants.into_boxed();
+    judge_tokens: &JudgeTokens,
+    code: &str,
+    seat_value: i32
+) -> Result<JudgeTokenResponse, AppError> {
+    let room = retrieve_room_by_join_code(conn, code)?;
 
-    if let Some(room_id_parameter) = room_id_parameter {
-        query = query.filter(room_id.eq(room_id_parameter));
-    }
+    return conn.transaction(|conn| {
+        use crate::schema::judges::dsl::*;
+        let judge: Judge = judges
+            .filter(room_id.eq(&room.id))
+            .filter(seat.eq(seat_value))
+            .for_update()
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("No judge is seated in seat {}", seat_value)))?;
+        if judge.claimed_at.is_some() {
+            return Err(
+                AppError::Conflict(format!("seat {} has already been claimed; ask the host to release it", seat_value))
+            );
+        }
 
-    let results = query.load::<Participant>(conn).expect("Error loading participants");
+        let nonce = Uuid::new_v4().simple().to_string();
+        let judge: Judge = diesel
+            ::update(judges.find(&judge.id))
+            .set((claimed_at.eq(Some(iso_date())), token_nonce.eq(Some(&nonce))))
+            .get_result(conn)?;
 
-    return results;
+        let (token, expires_at) = judge_tokens.sign(&judge.id, &room.join_code, &nonce);
+        Ok(JudgeTokenResponse {
+            token,
+            expires_at: expires_at.to_rfc3339(),
+            room_id: room.id.clone(),
+            judge,
+        })
+    });
 }
 
-pub fn retrieve_scores(
+/// Issues a credential for the room. Judge credentials are tied to a seated
+/// judge, so that judge is the only one they can score as.
+pub fn insert_credential(
     conn: &mut PgConnection,
-    participation_id_parameter: &Option<String>,
-    submitter_id_parameter: &Option<String>
-) -> Vec<Score> {
-    use crate::schema::scores::dsl::*;
+    room_id_value: &str,
+    role_value: Role,
+    judge_id_value: Option<String>,
+    label_value: Option<String>
+) -> Result<CredentialResponse, AppError> {
+    find_room(conn, room_id_value)?;
+    match (role_value, &judge_id_value) {
+        (Role::Judge, Some(judge_id_value)) => {
+            if retrieve_judge(conn, judge_id_value)?.room_id != room_id_value {
+                return Err(AppError::validation("judge_id must be a judge seated in this room"));
+            }
+        }
+        (Role::Judge, None) => {
+            return Err(AppError::validation("judge credentials need a judge_id"));
+        }
+        (_, Some(_)) => {
+            return Err(AppError::validation("only judge credentials take a judge_id"));
+        }
+        (_, None) => {}
+    }
 
-    let mut query = scores.into_boxed();
+    let new_token = generate_token();
+    let new_credential = RoomCredential {
+        id: Uuid::new_v4().to_string(),
+        room_id: room_id_value.to_owned(),
+        role: role_value.as_str().to_owned(),
+        judge_id: judge_id_value,
+        label: label_value,
+        token_hash: hash_token(&new_token),
+        created: iso_date(),
+    };
 
-    if let Some(participation_id_parameter) = participation_id_parameter {
-        query = query.filter(participation_id.eq(participation_id_parameter));
+    use crate::schema::room_credentials::dsl::*;
+    diesel::insert_into(room_credentials).values(&new_credential).execute(conn)?;
+
+    return Ok(CredentialResponse { credential: new_credential, token: new_token });
+}
+
+pub fn retrieve_credentials(
+    conn: &mut PgConnection,
+    room_id_parameter: &str
+) -> Result<Vec<RoomCredential>, AppError> {
+    use crate::schema::room_credentials::dsl::*;
+    let results = room_credentials
+        .filter(room_id.eq(room_id_parameter))
+        .order(created.asc())
+        .load::<RoomCredential>(conn)?;
+
+    return Ok(results);
+}
+
+pub fn retrieve_credential(
+    conn: &mut PgConnection,
+    credential_id_parameter: &str
+) -> Result<RoomCredential, AppError> {
+    return crate::schema::room_credentials::table
+        .find(credential_id_parameter)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| not_found("credential", credential_id_parameter));
+}
+
+/// Issues a host credential to every room that has none, such as rooms made
+/// before credentials existed. Without one, a room that no organization owns
+/// can't be run or claimed by anyone.
+pub fn issue_missing_host_credentials(conn: &mut PgConnection) -> Result<Vec<CredentialResponse>, AppError> {
+    return conn.transaction(|conn| {
+        let hosted_room_ids = crate::schema::room_credentials::table
+            .filter(crate::schema::room_credentials::role.eq(Role::Host.as_str()))
+            .select(crate::schema::room_credentials::room_id);
+        let unhosted_room_ids: Vec<String> = crate::schema::rooms::table
+            .filter(crate::schema::rooms::id.ne_all(hosted_room_ids))
+            .order(crate::schema::rooms::created.asc())
+            .select(crate::schema::rooms::id)
+            .load(conn)?;
+
+        let mut issued = Vec::new();
+        for room_id_value in &unhosted_room_ids {
+            issued.push(insert_credential(conn, room_id_value, Role::Host, None, Some("host".to_owned()))?);
+        }
+        Ok(issued)
+    });
+}
+
+/// Moves a room that no organization owns yet, such as one made before
+/// organizations existed, into one of the organizer's organizations. Holding
+/// one of the room's host credentials is what proves it is theirs to claim.
+pub fn claim_room(
+    conn: &mut PgConnection,
+    organizer: &Organizer,
+    room_id_parameter: &str,
+    host_token: &str,
+    requested: Option<&str>
+) -> Result<Room, AppError> {
+    return conn.transaction(|conn| {
+        let room = lock_room(conn, room_id_parameter)?;
+        let host_credentials: i64 = crate::schema::room_credentials::table
+            .filter(crate::schema::room_credentials::room_id.eq(&room.id))
+            .filter(crate::schema::room_credentials::role.eq(Role::Host.as_str()))
+            .filter(crate::schema::room_credentials::token_hash.eq(hash_token(host_token)))
+            .count()
+            .get_result(conn)?;
+        if host_credentials == 0 {
+            return Err(AppError::Forbidden("host_token is not a host credential for this room".to_owned()));
+        }
+        if room.organization_id.is_some() {
+            return Err(AppError::Conflict(format!("room {} already belongs to an organization", room.id)));
+        }
+        let claimant = resolve_room_membership(conn, organizer, requested)?;
+
+        let claimed: Room = diesel
+            ::update(crate::schema::rooms::table.find(&room.id))
+            .set((
+                crate::schema::rooms::organizer_id.eq(&claimant.organizer_id),
+                crate::schema::rooms::organization_id.eq(&claimant.organization_id),
+            ))
+            .get_result(conn)?;
+        diesel
+            ::update(crate::schema::participants::table.filter(crate::schema::participants::room_id.eq(&room.id)))
+            .set(crate::schema::participants::organization_id.eq(&claimant.organization_id))
+            .execute(conn)?;
+        diesel
+            ::update(crate::schema::judges::table.filter(crate::schema::judges::room_id.eq(&room.id)))
+            .set(crate::schema::judges::organization_id.eq(&claimant.organization_id))
+            .execute(conn)?;
+
+        Ok(claimed)
+    });
+}
+
+/// Revokes a credential. A room always keeps at least one host.
+pub fn remove_credential(conn: &mut PgConnection, id_value: String) -> Result<RoomCredential, AppError> {
+    return conn.transaction(|conn| {
+        let credential = retrieve_credential(conn, &id_value)?;
+        lock_room(conn, &credential.room_id)?;
+
+        use crate::schema::room_credentials::dsl::*;
+        if credential.role() == Role::Host {
+            let hosts: i64 = room_credentials
+                .filter(room_id.eq(&credential.room_id))
+                .filter(role.eq(Role::Host.as_str()))
+                .count()
+                .get_result(conn)?;
+            if hosts <= 1 {
+                return Err(AppError::Conflict("a room must keep at least one host credential".to_owned()));
+            }
+        }
+
+        diesel::delete(room_credentials.find(&id_value)).execute(conn)?;
+        Ok(credential)
+    });
+}
+
+pub fn insert_participant(
+    conn: &mut PgConnection,
+    name_value: &str,
+    pronouns_value: Option<String>,
+    room_id_value: &str,
+    competitive_value: bool
+) -> Result<Participant, AppError> {
+    let room = find_room(conn, room_id_value)?;
+
+    use crate::schema::participants::dsl::*;
+    let new_participant = Participant {
+        id: Uuid::new_v4().to_string(),
+        name: name_value.to_owned(),
+        pronouns: pronouns_value,
+        room_id: room_id_value.to_string(),
+        organization_id: room.organization_id,
+        competitive: competitive_value,
+    };
+    let existing_participant: Option<Participant> = participants
+        .filter(name.eq(name_value).and(room_id.eq(room_id_value)))
+        .first(conn)
+        .optional()?;
+    if existing_participant.is_some() {
+        return Err(
+            AppError::Conflict(format!("{} is already a participant in this room", name_value))
+        );
     }
-    
-    if let Some(submitter_id_parameter) = submitter_id_parameter {
-        query = query.filter(submitter_id.eq(submitter_id_parameter));
+
+    diesel
+        ::insert_into(participants)
+        .values(&new_participant)
+        .execute(conn)?;
+    return Ok(new_participant);
+}
+
+/// Seats a judge in the room. Without an explicit seat the judge takes the
+/// lowest free one; seats run from 1 to the room's judge count.
+pub fn insert_judge(
+    conn: &mut PgConnection,
+    room_id_value: &str,
+    seat_value: Option<i32>,
+    label_value: Option<String>
+) -> Result<Judge, AppError> {
+    let room = find_room(conn, room_id_value)?;
+
+    use crate::schema::judges::dsl::*;
+    let taken_seats: Vec<i32> = judges
+        .filter(room_id.eq(room_id_value))
+        .select(seat)
+        .load::<i32>(conn)?;
+
+    let new_seat = match seat_value {
+        Some(seat_value) => seat_value,
+        None =>
+            match (1..=room.judge_count).find(|free_seat| !taken_seats.contains(free_seat)) {
+                Some(free_seat) => free_seat,
+                None => {
+                    return Err(
+                        AppError::Conflict(format!("all {} judge seats are taken", room.judge_count))
+                    );
+                }
+            }
+    };
+    if new_seat < 1 || new_seat > room.judge_count {
+        return Err(AppError::validation(format!("seat must be between 1 and {}", room.judge_count)));
+    }
+    if taken_seats.contains(&new_seat) {
+        return Err(AppError::Conflict(format!("seat {} is already taken", new_seat)));
     }
 
-    let results = query.load::<Score>(conn).expect("Error loading scores");
+    let new_judge = Judge {
+        id: Uuid::new_v4().to_string(),
+        room_id: room_id_value.to_owned(),
+        seat: new_seat,
+        label: label_value.unwrap_or_else(|| judge_label(new_seat)),
+        organization_id: room.organization_id,
+        claimed_at: None,
+        token_nonce: None,
+    };
+    diesel::insert_into(judges).values(&new_judge).execute(conn)?;
 
-    return results;
+    return Ok(new_judge);
 }
 
-fn iso_date() -> String {
-    let now = SystemTime::now();
-    let now: DateTime<Utc> = now.into();
-    return now.to_rfc3339();
+pub fn update_judge(
+    conn: &mut PgConnection,
+    id_value: String,
+    label_value: Option<String>
+) -> Result<Judge, AppError> {
+    use crate::schema::judges::dsl::*;
+    let result = diesel
+        ::update(judges.filter(id.eq(&id_value)))
+        .set(
+            &(JudgeUpdate {
+                label: label_value,
+            })
+        )
+        .get_result::<Judge>(conn)
+        .optional()?
+        .ok_or_else(|| not_found("judge", &id_value))?;
+    return Ok(result);
 }
 
-pub fn establish_connection() -> PgConnection {
-    dotenv().ok();
+/// Removes a judge along with their scores, so they no longer count towards
+/// any aggregate and a replacement judge can score the same performances.
+pub fn remove_judge(conn: &mut PgConnection, id_value: String) -> Result<Judge, AppError> {
+    return conn.transaction(|conn| {
+        let judge = retrieve_judge(conn, &id_value)?;
+        let room = lock_room(conn, &judge.room_id)?;
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    PgConnection::establish(&database_url).unwrap_or_else(|_|
-        panic!("Error connecting to {}", database_url)
-    )
-}
\ No newline at end of file
+        use crate::schema::judges::dsl::*;
+        let result = diesel
+            ::delete(judges.filter(id.eq(&id_value)))
+            .get_result::<Judge>(conn)
+            .optional()?
+            .ok_or_else(|| not_found("judge", &id_value))?;
+
+        let rescored_participation_ids: Vec<String> = diesel
+            ::delete(crate::schema::scores::table.filter(crate::schema::scores::submitter_id.eq(&id_value)))
+            .returning(crate::schema::scores::participation_id)
+            .get_results(conn)?;
+        let policy = ScoringPolicy::from_room(&room);
+        let seats = seated_judge_ids(conn, &room.id, room.judge_count)?;
+        for participation_id_value in &rescored_participation_ids {
+            refresh_aggregate(conn, &policy, &seats, participation_id_value)?;
+        }
+
+        Ok(result)
+    });
+}
+
+/// Judges whose scores count in a room: those seated within its judge count.
+/// Seats left above a lowered judge count neither score nor count.
+fn seated_judge_ids(
+    conn: &mut PgConnection,
+    room_id_value: &str,
+    judge_count_value: i32
+) -> Result<Vec<String>, AppError> {
+    use crate::schema::judges::dsl::*;
+    let results = judges
+        .filter(room_id.eq(room_id_value))
+        .filter(seat.le(judge_count_value))
+        .select(id)
+        .load::<String>(conn)?;
+    return Ok(results);
+}
+
+/// Judges of a room, within the caller's tenant.
+pub fn retrieve_judges(
+    conn: &mut PgConnection,
+    tenant: Option<&str>,
+    room_id_parameter: &str
+) -> Result<Vec<Judge>, AppError> {
+    use crate::schema::judges::dsl::*;
+
+    let results = judges
+        .filter(organization_id.is_not_distinct_from(tenant))
+        .filter(room_id.eq(room_id_parameter))
+        .order(seat.asc())
+        .load::<Judge>(conn)?;
+
+    return Ok(results);
+}
+
+pub fn update_room(
+    conn: &mut PgConnection,
+    id_value: String,
+    name_value: Option<String>,
+    policy_value: Option<ScoringPolicy>,
+    tie_break_method_value: Option<TieBreakMethod>,
+    cut_sizes_value: Option<Vec<i32>>,
+    range_value: Option<ScoreRange>
+) -> Result<Room, AppError> {
+    return conn.transaction(|conn| {
+        let room = lock_room(conn, &id_value)?;
+
+        // Performances already scored are re-aggregated under the new policy.
+        let mut rescore = None;
+        if let Some(policy) = &policy_value {
+            let seats = seated_judge_ids(conn, &id_value, policy.judge_count)?;
+            let scored_participation_ids: Vec<String> = crate::schema::scores::table
+                .inner_join(crate::schema::participations::table.inner_join(crate::schema::rounds::table))
+                .filter(crate::schema::rounds::room_id.eq(&id_value))
+                .select(crate::schema::scores::participation_id)
+                .distinct()
+                .load(conn)?;
+            // Only a new judge count is held to the seats; changing the mode or
+            // drop count of a room that is short of judges is fine.
+            let judge_count_changed = policy.judge_count != room.judge_count;
+            let short_of_judges = (policy.judge_count as usize) > seats.len();
+            if judge_count_changed && short_of_judges && !scored_participation_ids.is_empty() {
+                return Err(AppError::Validation {
+                    message: format!(
+                        "judge_count can't exceed the {} seated judges once scoring has started",
+                        seats.len()
+                    ),
+                    details: Some(serde_json::json!({ "field": "judge_count" })),
+                });
+            }
+            rescore = Some((seats, scored_participation_ids));
+        }
+
+        use crate::schema::rooms::dsl::*;
+        let result = diesel
+            ::update(rooms.filter(id.eq(&id_value)))
+            .set(
+                &(RoomUpdate {
+                    name: name_value,
+                    judge_count: policy_value.as_ref().map(|policy| policy.judge_count),
+                    drop_count: policy_value.as_ref().map(|policy| policy.drop_count),
+                    aggregation_mode: policy_value
+                        .as_ref()
+                        .map(|policy| policy.aggregation_mode.as_str().to_owned()),
+                    tie_break_method: tie_break_method_value.map(|method| method.as_str().to_owned()),
+                    cut_sizes: cut_sizes_value,
+                    score_min: range_value.as_ref().map(|range| range.min),
+                    score_max: range_value.as_ref().map(|range| range.max),
+                    score_precision: range_value.as_ref().map(|range| range.precision),
+                })
+            )
+            .get_result::<Room>(conn)
+            .optional()?
+            .ok_or_else(|| not_found("room", &id_value))?;
+
+        if let (Some(policy), Some((seats, scored_participation_ids))) = (&policy_value, rescore) {
+            for participation_id_value in &scored_participation_ids {
+                refresh_aggregate(conn, policy, &seats, participation_id_value)?;
+            }
+        }
+
+        Ok(result)
+    });
+}
+
+pub fn update_participant(
+    conn: &mut PgConnection,
+    id_value: String,
+    name_value: Option<String>,
+    pronouns_value: Option<String>,
+    competitive_value: Option<bool>
+) -> Result<Participant, AppError> {
+    use crate::schema::participants::dsl::*;
+    let result = diesel
+        ::update(participants.filter(id.eq(&id_value)))
+        .set(
+            &(ParticipantUpdate {
+                name: name_value,
+                pronouns: pronouns_value,
+                competitive: competitive_value,
+            })
+        )
+        .get_result::<Participant>(conn)
+        .optional()?
+        .ok_or_else(|| not_found("participant", &id_value))?;
+    return Ok(result);
+}
+
+pub fn update_participation(
+    conn: &mut PgConnection,
+    id_value: String,
+    notes_value: Option<String>,
+    length_value: Option<i32>,
+    competitive_value: Option<bool>
+) -> Result<Participation, AppError> {
+    use crate::schema::participations::dsl::*;
+    let mut deduction_value = None;
+    if let Some(length) = length_value {
+        let participation = find_participation(conn, &id_value)?;
+        let round = find_round(conn, &participation.round_id)?;
+        let settings = retrieve_time_penalty(conn, &round.room_id, Some(&round.id))?;
+        deduction_value = Some(compute_deduction(&settings, length));
+    }
+
+    let result = diesel
+        ::update(participations.filter(id.eq(&id_value)))
+        .set(
+            &(ParticipationUpdate {
+                performance_length_in_seconds: length_value,
+                performance_notes: notes_value,
+                deduction: deduction_value,
+                score: None,
+                competitive: competitive_value,
+            })
+        )
+        .get_result::<Participation>(conn)
+        .optional()?
+        .ok_or_else(|| not_found("participation", &id_value))?;
+    return Ok(result);
+}
+
+/// Starts timing a performance on the server's clock.
+pub fn start_timer(
+    conn: &mut PgConnection,
+    participation_id_parameter: &str
+) -> Result<Participation, AppError> {
+    return conn.transaction(|conn| {
+        let participation = lock_participation(conn, participation_id_parameter)?;
+        if participation.timer_stopped_at.is_some() {
+            return Err(AppError::Conflict("timer was already stopped; reset it to time again".to_owned()));
+        }
+        if participation.timer_started_at.is_some() {
+            return Err(AppError::Conflict("timer is already running".to_owned()));
+        }
+
+        use crate::schema::participations::dsl::*;
+        let result = diesel
+            ::update(participations.find(participation_id_parameter))
+            .set(timer_started_at.eq(iso_date()))
+            .get_result::<Participation>(conn)?;
+        Ok(result)
+    });
+}
+
+/// Stops the timer and records the measured length along with its deduction.
+pub fn stop_timer(
+    conn: &mut PgConnection,
+    participation_id_parameter: &str
+) -> Result<Participation, AppError> {
+    return conn.transaction(|conn| {
+        let participation = lock_participation(conn, participation_id_parameter)?;
+        if participation.timer_stopped_at.is_some() {
+            return Err(AppError::Conflict("timer was already stopped".to_owned()));
+        }
+        let started_at = participation.timer_started_at.ok_or_else(||
+            AppError::Conflict("timer is not running".to_owned())
+        )?;
+        let stopped_at = iso_date();
+        let length = elapsed_seconds(&started_at, &stopped_at)?;
+        let round = find_round(conn, &participation.round_id)?;
+        let settings = retrieve_time_penalty(conn, &round.room_id, Some(&round.id))?;
+
+        use crate::schema::participations::dsl::*;
+        let result = diesel
+            ::update(participations.find(participation_id_parameter))
+            .set((
+                timer_stopped_at.eq(stopped_at),
+                performance_length_in_seconds.eq(length),
+                deduction.eq(compute_deduction(&settings, length)),
+            ))
+            .get_result::<Participation>(conn)?;
+        Ok(result)
+    });
+}
+
+/// Clears the timer and whatever length it measured, so the poem can be timed again.
+pub fn reset_timer(
+    conn: &mut PgConnection,
+    participation_id_parameter: &str
+) -> Result<Participation, AppError> {
+    return conn.transaction(|conn| {
+        lock_participation(conn, participation_id_parameter)?;
+
+        use crate::schema::participations::dsl::*;
+        let result = diesel
+            ::update(participations.find(participation_id_parameter))
+            .set((
+                timer_started_at.eq(None::<String>),
+                timer_stopped_at.eq(None::<String>),
+                performance_length_in_seconds.eq(None::<i32>),
+                deduction.eq(None::<f32>),
+            ))
+            .get_result::<Participation>(conn)?;
+        Ok(result)
+    });
+}
+
+/// The room's participants by id, after checking that every one of
+/// `participant_ids` is among them. Participant ids come from requests, so
+/// nobody from another room (or another organization) may be slipped into one
+/// of the room's rounds. Foreign ids are reported as missing rather than
+/// confirming they exist elsewhere.
+fn check_room_participants<'a>(
+    conn: &mut PgConnection,
+    room: &Room,
+    participant_ids: impl Iterator<Item = &'a str>
+) -> Result<HashMap<String, Participant>, AppError> {
+    let room_participants: HashMap<String, Participant> = retrieve_participants(
+        conn,
+        room.organization_id.as_deref(),
+        &room.id
+    )?
+        .into_iter()
+        .map(|participant| (participant.id.clone(), participant))
+        .collect();
+    for participant_id_value in participant_ids {
+        if !room_participants.contains_key(participant_id_value) {
+            return Err(not_found("participant", participant_id_value));
+        }
+    }
+    return Ok(room_participants);
+}
+
+/// Adds a single participation to an existing round, e.g. the sacrificial poet.
+/// Without an explicit `performance_order_value` it goes after everyone else;
+/// with one, later performers shift back to make room.
+pub fn insert_participation(
+    conn: &mut PgConnection,
+    round_id_parameter: &str,
+    participant_id_value: &str,
+    competitive_value: Option<bool>,
+    performance_order_value: Option<i32>
+) -> Result<Participation, AppError> {
+    return conn.transaction(|conn| {
+        let round = find_round(conn, round_id_parameter)?;
+        let room = find_room(conn, &round.room_id)?;
+        let room_participants = check_room_participants(conn, &room, std::iter::once(participant_id_value))?;
+        let competitive_value = competitive_value.unwrap_or(room_participants[participant_id_value].competitive);
+
+        use crate::schema::participations::dsl::*;
+
+        let last_order: Option<i32> = participations
+            .filter(round_id.eq(round_id_parameter))
+            .select(diesel::dsl::max(performance_order))
+            .first(conn)?;
+
+        let new_order = match performance_order_value {
+            Some(order) => {
+                diesel
+                    ::update(
+                        participations
+                            .filter(round_id.eq(round_id_parameter))
+                            .filter(performance_order.ge(order))
+                    )
+                    .set(performance_order.eq(performance_order + 1))
+                    .execute(conn)?;
+                order
+            }
+            None => last_order.map_or(0, |order| order + 1),
+        };
+
+        let new_participation = Participation {
+            id: Uuid::new_v4().to_string(),
+            performance_notes: None,
+            performance_length_in_seconds: None,
+            deduction: None,
+            score: None,
+            performance_order: new_order,
+            round_id: round_id_parameter.to_owned(),
+            participant_id: participant_id_value.to_owned(),
+            competitive: competitive_value,
+            timer_started_at: None,
+            timer_stopped_at: None,
+        };
+
+        diesel
+            ::insert_into(participations)
+            .values(&new_participation)
+            .execute(conn)?;
+
+        Ok(new_participation)
+    });
+}
+
+pub fn retrieve_time_penalty(
+    conn: &mut PgConnection,
+    room_id_parameter: &str,
+    round_id_parameter: Option<&str>
+) -> Result<TimePenaltySettings, AppError> {
+    use crate::schema::time_penalty_policies::dsl::*;
+
+    if let Some(round_id_parameter) = round_id_parameter {
+        let round_policy: Option<TimePenaltyPolicy> = time_penalty_policies
+            .filter(round_id.eq(round_id_parameter))
+            .first(conn)
+            .optional()?;
+        if let Some(policy) = round_policy {
+            return Ok(TimePenaltySettings::from(&policy));
+        }
+    }
+
+    let room_policy: Option<TimePenaltyPolicy> = time_penalty_policies
+        .filter(room_id.eq(room_id_parameter))
+        .filter(round_id.is_null())
+        .first(conn)
+        .optional()?;
+
+    return Ok(room_policy.map(|policy| TimePenaltySettings::from(&policy)).unwrap_or_default());
+}
+
+/// Stores the room default (`round_id_parameter` of `None`) or a round override,
+/// then recalculates every deduction in the room.
+pub fn upsert_time_penalty(
+    conn: &mut PgConnection,
+    room_id_parameter: &str,
+    round_id_parameter: Option<&str>,
+    settings: &TimePenaltySettings
+) -> Result<TimePenaltyPolicy, AppError> {
+    // The room lock keeps two saves from both inserting a policy, and the
+    // deductions are only ever seen matching the policy that produced them.
+    return conn.transaction(|conn| {
+        lock_room(conn, room_id_parameter)?;
+
+        use crate::schema::time_penalty_policies::dsl::*;
+
+        let mut query = time_penalty_policies.filter(room_id.eq(room_id_parameter)).into_boxed();
+        query = match round_id_parameter {
+            Some(round_id_parameter) => query.filter(round_id.eq(round_id_parameter)),
+            None => query.filter(round_id.is_null()),
+        };
+        let existing_policy: Option<TimePenaltyPolicy> = query
+            .first(conn)
+            .optional()?;
+
+        let policy = TimePenaltyPolicy {
+            id: existing_policy.map_or_else(|| Uuid::new_v4().to_string(), |existing| existing.id),
+            room_id: room_id_parameter.to_owned(),
+            round_id: round_id_parameter.map(|value| value.to_owned()),
+            time_limit_seconds: settings.time_limit_seconds,
+            grace_seconds: settings.grace_seconds,
+            increment_seconds: settings.increment_seconds,
+            penalty_per_increment: settings.penalty_per_increment,
+            penalty_cap: settings.penalty_cap,
+            no_penalty: settings.no_penalty,
+        };
+
+        diesel
+            ::insert_into(time_penalty_policies)
+            .values(&policy)
+            .on_conflict(id)
+            .do_update()
+            .set((
+                time_limit_seconds.eq(policy.time_limit_seconds),
+                grace_seconds.eq(policy.grace_seconds),
+                increment_seconds.eq(policy.increment_seconds),
+                penalty_per_increment.eq(policy.penalty_per_increment),
+                penalty_cap.eq(policy.penalty_cap),
+                no_penalty.eq(policy.no_penalty),
+            ))
+            .execute(conn)?;
+
+        recalculate_deductions(conn, room_id_parameter)?;
+
+        Ok(policy)
+    });
+}
+
+pub fn remove_round_time_penalty(
+    conn: &mut PgConnection,
+    round_id_parameter: &str
+) -> Result<usize, AppError> {
+    return conn.transaction(|conn| {
+        let round = find_round(conn, round_id_parameter)?;
+        lock_room(conn, &round.room_id)?;
+
+        use crate::schema::time_penalty_policies::dsl::*;
+        let result = diesel
+            ::delete(time_penalty_policies.filter(round_id.eq(round_id_parameter)))
+            .execute(conn)?;
+
+        recalculate_deductions(conn, &round.room_id)?;
+
+        Ok(result)
+    });
+}
+
+/// Re-applies the effective time penalty to every timed participation in the room.
+pub fn recalculate_deductions(
+    conn: &mut PgConnection,
+    room_id_parameter: &str
+) -> Result<usize, AppError> {
+    use crate::schema::rounds::dsl::*;
+    let round_results = rounds
+        .filter(room_id.eq(room_id_parameter))
+        .load::<Round>(conn)?;
+
+    let mut result = 0;
+    for round in round_results {
+        let settings = retrieve_time_penalty(conn, room_id_parameter, Some(&round.id))?;
+
+        use crate::schema::participations::dsl::*;
+        let timed_participations = Participation::belonging_to(&round)
+            .filter(performance_length_in_seconds.is_not_null())
+            .load::<Participation>(conn)?;
+
+        for participation in timed_participations {
+            let length = participation.performance_length_in_seconds.unwrap_or_default();
+            result += diesel
+                ::update(participations.filter(crate::schema::participations::id.eq(participation.id)))
+                .set(deduction.eq(compute_deduction(&settings, length)))
+                .execute(conn)?;
+        }
+    }
+
+    return Ok(result);
+}
+
+pub fn remove_room(conn: &mut PgConnection, id_value: String) -> Result<Room, AppError> {
+    use crate::schema::rooms::dsl::*;
+
+    let result = diesel
+        ::delete(rooms.filter(id.eq(&id_value)))
+        .get_result::<Room>(conn)
+        .optional()?
+        .ok_or_else(|| not_found("room", &id_value))?;
+    
+    return Ok(result);
+}
+
+pub fn remove_participant(conn: &mut PgConnection, id_value: String) -> Result<Participant, AppError> {
+    use crate::schema::participants::dsl::*;
+    let result = diesel
+        ::delete(participants.filter(id.eq(&id_value)))
+        .get_result::<Participant>(conn)
+        .optional()?
+        .ok_or_else(|| not_found("participant", &id_value))?;
+    return Ok(result);
+}
+
+pub fn insert_score(
+    conn: &mut PgConnection,
+    value_value: &f32,
+    participation_id_value: &str,
+    submitter_id_value: &str
+) -> Result<Score, AppError> {
+    return conn.transaction(|conn| {
+        // Locking the participation queues up judges scoring the same poem, so the
+        // seat check and the aggregate below always see every committed score.
+        lock_participation(conn, participation_id_value)?;
+
+        let room = retrieve_participation_room(conn, participation_id_value)?;
+        let policy = ScoringPolicy::from_room(&room);
+        let value_value = validate_score(&ScoreRange::from_room(&room), *value_value)?;
+
+        let seats = seated_judge_ids(conn, &room.id, room.judge_count)?;
+        if !seats.iter().any(|seated_id| seated_id == submitter_id_value) {
+            return Err(
+                AppError::Forbidden("submitter is not seated as a judge in this room".to_owned())
+            );
+        }
+
+        use crate::schema::scores::dsl::*;
+        let submitted_scores: Vec<Score> = scores
+            .filter(participation_id.eq(participation_id_value))
+            .filter(submitter_id.eq_any(&seats))
+            .load::<Score>(conn)?;
+        let resubmission = submitted_scores
+            .iter()
+            .any(|score| score.submitter_id.as_deref() == Some(submitter_id_value));
+        if !resubmission && submitted_scores.len() >= seats.len() {
+            return Err(
+                AppError::Conflict(
+                    format!("all {} judge seats have already scored this performance", seats.len())
+                )
+            );
+        }
+
+        let value_value = i32::try_from(to_fixed(value_value)).map_err(|_| {
+            return AppError::validation("value is out of range");
+        })?;
+        let new_score = Score {
+            id: Uuid::new_v4().to_string(),
+            value: value_value,
+            submitter_id: Some(submitter_id_value.to_string()),
+            participation_id: participation_id_value.to_string(),
+        };
+        let result: Score = diesel
+            ::insert_into(scores)
+            .values(&new_score)
+            .on_conflict((participation_id, submitter_id))
+            .do_update()
+            .set(value.eq(value_value))
+            .get_result(conn)?;
+
+        refresh_aggregate(conn, &policy, &seats, participation_id_value)?;
+
+        Ok(result)
+    });
+}
+
+/// Scores a performance needs before it is aggregated: one from every seated
+/// judge, up to the room's judge count.
+fn expected_score_count(policy: &ScoringPolicy, seat_count: usize) -> usize {
+    return seat_count.min(policy.judge_count as usize);
+}
+
+/// Re-aggregates a participation from the scores of the judges in `seats`. The
+/// score stays unset until every expected score is in.
+fn refresh_aggregate(
+    conn: &mut PgConnection,
+    policy: &ScoringPolicy,
+    seats: &[String],
+    participation_id_value: &str
+) -> Result<(), AppError> {
+    let values: Vec<i64> = crate::schema::scores::table
+        .filter(crate::schema::scores::participation_id.eq(participation_id_value))
+        .filter(crate::schema::scores::submitter_id.eq_any(seats))
+        .order(crate::schema::scores::value.asc())
+        .select(crate::schema::scores::value)
+        .load::<i32>(conn)?
+        .into_iter()
+        .map(i64::from)
+        .collect();
+
+    let expected = expected_score_count(policy, seats.len());
+    let aggregate_score = if expected > 0 && values.len() >= expected {
+        let total = aggregate(policy, &values);
+        Some(
+            i32
+                ::try_from(total)
+                .map_err(|_| AppError::Internal(format!("aggregate score {} does not fit in i32", total)))?
+        )
+    } else {
+        None
+    };
+
+    use crate::schema::participations::dsl::*;
+    diesel
+        ::update(participations.filter(id.eq(participation_id_value)))
+        .set(score.eq(aggregate_score))
+        .execute(conn)?;
+
+    return Ok(());
+}
+
+/// Rooms by id, within the caller's tenant.
+pub fn retrieve_rooms(
+    conn: &mut PgConnection,
+    tenant: Option<&str>,
+    room_ids: &[String]
+) -> Result<Vec<Room>, AppError> {
+    use crate::schema::rooms::dsl::*;
+    let results = rooms
+        .filter(organization_id.is_not_distinct_from(tenant))
+        .filter(id.eq_any(room_ids))
+        .order(created.desc())
+        .load::<Room>(conn)?;
+
+    return Ok(results);
+}
+
+/// Rooms of every organization the organizer belongs to, or of just one of them.
+pub fn retrieve_member_rooms(
+    conn: &mut PgConnection,
+    organizer_id_value: &str,
+    organization_id_parameter: Option<&str>
+) -> Result<Vec<Room>, AppError> {
+    use crate::schema::organization_members;
+    use crate::schema::rooms::dsl::*;
+    let memberships = organization_members::table
+        .filter(organization_members::organizer_id.eq(organizer_id_value))
+        .select(organization_members::organization_id);
+    let mut query = rooms
+        .filter(organization_id.eq_any(memberships.nullable()))
+        .into_boxed();
+    if let Some(organization_id_parameter) = organization_id_parameter {
+        query = query.filter(organization_id.eq(organization_id_parameter));
+    }
+    let results = query.order(created.desc()).load::<Room>(conn)?;
+
+    return Ok(results);
+}
+
+pub fn retrieve_participation(
+    conn: &mut PgConnection,
+    participation_id_parameter: &str
+) -> Result<ParticipationResponse, AppError> {
+    let participation = find_participation(conn, participation_id_parameter)?;
+    let participant: Participant = crate::schema::participants::table
+        .find(&participation.participant_id)
+        .first(conn)?;
+
+    return Ok(ParticipationResponse::new(participation, participant));
+}
+
+pub fn retrieve_participant(
+    conn: &mut PgConnection,
+    participant_id_parameter: &str
+) -> Result<Participant, AppError> {
+    return crate::schema::participants::table
+        .find(participant_id_parameter)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| not_found("participant", participant_id_parameter));
+}
+
+pub fn retrieve_judge(conn: &mut PgConnection, judge_id_parameter: &str) -> Result<Judge, AppError> {
+    return crate::schema::judges::table
+        .find(judge_id_parameter)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| not_found("judge", judge_id_parameter));
+}
+
+pub fn retrieve_participation_room(
+    conn: &mut PgConnection,
+    participation_id_parameter: &str
+) -> Result<Room, AppError> {
+    let participation = find_participation(conn, participation_id_parameter)?;
+    let round = find_round(conn, &participation.round_id)?;
+    let room = find_room(conn, &round.room_id)?;
+
+    return Ok(room);
+}
+
+pub fn retrieve_round_room(conn: &mut PgConnection, round_id_parameter: &str) -> Result<Room, AppError> {
+    let round = find_round(conn, round_id_parameter)?;
+    let room = find_room(conn, &round.room_id)?;
+
+    return Ok(room);
+}
+
+pub fn retrieve_room(conn: &mut PgConnection, room_id_parameter: &str) -> Result<RoomResponse, AppError> {
+    let room_results = find_room(conn, room_id_parameter)?;
+
+    use crate::schema::participants::dsl::*;
+    let match_value = room_id_parameter.to_owned();
+    let participant_results = participants
+        .filter(crate::schema::participants::dsl::room_id.eq(match_value.clone()))
+        .load::<Participant>(conn)?;
+
+    use crate::schema::rounds::dsl::*;
+    let round_results = rounds
+        .filter(crate::schema::rounds::dsl::room_id.eq(match_value))
+        .load::<Round>(conn)?;
+
+    let results = RoomResponse {
+        room: room_results,
+        participants: participant_results,
+        rounds: round_results,
+    };
+
+    return Ok(results);
+}
+
+pub fn retrieve_round(conn: &mut PgConnection, round_id_parameter: &str) -> Result<RoundResponse, AppError> {
+    let round_results = find_round(conn, round_id_parameter)?;
+
+    use crate::schema::participants::dsl::*;
+
+    let participation_results: Vec<(Participation, Participant)> = Participation::belonging_to(
+        &round_results
+    )
+        .order(performance_order.asc())
+        .inner_join(participants)
+        .select((Participation::as_select(), Participant::as_select()))
+        .load::<(Participation, Participant)>(conn)?;
+
+    let transformed_participation_results = participation_results
+        .into_iter()
+        .map(|(participation, participant)| ParticipationResponse::new(participation, participant))
+        .collect();
+
+    let results = RoundResponse {
+        round: round_results,
+        participations: transformed_participation_results,
+    };
+
+    return Ok(results);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PerformerStep {
+    Next,
+    Previous,
+}
+
+pub fn retrieve_current_round(
+    conn: &mut PgConnection,
+    room_id_parameter: &str
+) -> Result<CurrentRoundResponse, AppError> {
+    let room = find_room(conn, room_id_parameter)?;
+    let round_id_value = room.round_id_current.ok_or_else(||
+        AppError::NotFound(format!("Room {} has no current round", room_id_parameter))
+    )?;
+    let round = retrieve_round(conn, &round_id_value)?;
+    let current_participation = room.participation_id_current.and_then(|participation_id_value| {
+        round.participations
+            .iter()
+            .find(|participation| participation.participation.id == participation_id_value)
+            .cloned()
+    });
+
+    return Ok(CurrentRoundResponse { round, current_participation });
+}
+
+/// Locks the room row so concurrent host actions move the performer one at a time.
+fn lock_room(conn: &mut PgConnection, room_id_parameter: &str) -> Result<Room, AppError> {
+    return crate::schema::rooms::table
+        .find(room_id_parameter)
+        .for_update()
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| not_found("room", room_id_parameter));
+}
+
+fn no_current_round(room_id_parameter: &str) -> AppError {
+    return AppError::Conflict(format!("Room {} has no current round", room_id_parameter));
+}
+
+/// Puts a participation from the room's current round on stage, or clears the
+/// stage when `participation_id_parameter` is `None`.
+pub fn update_current_participation(
+    conn: &mut PgConnection,
+    room_id_parameter: &str,
+    participation_id_parameter: Option<&str>
+) -> Result<CurrentRoundResponse, AppError> {
+    return conn.transaction(|conn| {
+        let room = lock_room(conn, room_id_parameter)?;
+        let current_round_id = room.round_id_current.ok_or_else(|| no_current_round(room_id_parameter))?;
+        if let Some(participation_id_value) = participation_id_parameter {
+            let participation = find_participation(conn, participation_id_value)?;
+            if participation.round_id != current_round_id {
+                return Err(AppError::validation("participation is not in the room's current round"));
+            }
+        }
+
+        use crate::schema::rooms::dsl::*;
+        diesel
+            ::update(rooms.find(room_id_parameter))
+            .set(participation_id_current.eq(participation_id_parameter))
+            .execute(conn)?;
+
+        retrieve_current_round(conn, room_id_parameter)
+    });
+}
+
+/// Moves the stage to the neighbouring participation by `performance_order`.
+/// With nobody on stage, `Next` starts from the first performer.
+pub fn step_current_participation(
+    conn: &mut PgConnection,
+    room_id_parameter: &str,
+    step: PerformerStep
+) -> Result<CurrentRoundResponse, AppError> {
+    return conn.transaction(|conn| {
+        let room = lock_room(conn, room_id_parameter)?;
+        let current_round_id = room.round_id_current.ok_or_else(|| no_current_round(room_id_parameter))?;
+
+        let running_order: Vec<String> = crate::schema::participations::table
+            .filter(crate::schema::participations::round_id.eq(&current_round_id))
+            .order(performance_order.asc())
+            .select(crate::schema::participations::id)
+            .load(conn)?;
+        let position = room.participation_id_current.and_then(|participation_id_value| {
+            running_order.iter().position(|id_value| *id_value == participation_id_value)
+        });
+
+        let target = match (step, position) {
+            (PerformerStep::Next, None) => running_order.first(),
+            (PerformerStep::Next, Some(position)) => running_order.get(position + 1),
+            (PerformerStep::Previous, None) => None,
+            (PerformerStep::Previous, Some(position)) =>
+                position.checked_sub(1).and_then(|position| running_order.get(position)),
+        };
+        let target = target.ok_or_else(|| {
+            match step {
+                PerformerStep::Next => AppError::Conflict("no one left to perform in this round".to_owned()),
+                PerformerStep::Previous => AppError::Conflict("no earlier performer in this round".to_owned()),
+            }
+        })?;
+
+        use crate::schema::rooms::dsl::*;
+        diesel
+            ::update(rooms.find(room_id_parameter))
+            .set(participation_id_current.eq(target))
+            .execute(conn)?;
+
+        retrieve_current_round(conn, room_id_parameter)
+    });
+}
+
+pub fn retrieve_round_standings(
+    conn: &mut PgConnection,
+    round_id_parameter: &str,
+    coin_flips: CoinFlips
+) -> Result<StandingsResponse, AppError> {
+    let round_response = retrieve_round(conn, round_id_parameter)?;
+    let room = find_room(conn, &round_response.round.room_id)?;
+    let policy = ScoringPolicy::from_room(&room);
+    let method = TieBreakMethod::parse(&room.tie_break_method).unwrap_or(TieBreakMethod::None);
+
+    let participation_ids: Vec<String> = round_response.participations
+        .iter()
+        .map(|participation| participation.participation.id.clone())
+        .collect();
+    let score_values = retrieve_score_values(conn, &participation_ids)?;
+
+    let items = round_response.participations
+        .into_iter()
+        .filter(|participation| participation.participation.competitive)
+        .map(|participation| {
+            let values = score_values
+                .get(&participation.participation.id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let (dropped_high, dropped_low) = dropped_scores(&policy, values);
+            let input = TieBreakInput {
+                participant_id: participation.participant.id.clone(),
+                net_score: participation.net_score,
+                dropped_high,
+                dropped_low,
+                coin_flip: None,
+            };
+            (input, participation)
+        })
+        .collect();
+
+    let (ranked, tie_breaks) = rank_tie_broken(
+        conn,
+        &room,
+        method,
+        round_id_parameter,
+        items,
+        coin_flips
+    )?;
+
+    let standings = ranked
+        .into_iter()
+        .map(|ranked| Standing {
+            rank: ranked.rank,
+            tied: ranked.tied,
+            participation: ranked.item,
+        })
+        .collect();
+
+    let results = StandingsResponse {
+        round: round_response.round,
+        tie_break_method: method.as_str().to_owned(),
+        standings,
+        tie_breaks,
+    };
+
+    return Ok(results);
+}
+
+pub fn retrieve_room_leaderboard(
+    conn: &mut PgConnection,
+    room_id_parameter: &str,
+    coin_flips: CoinFlips
+) -> Result<LeaderboardResponse, AppError> {
+    let room_results = find_room(conn, room_id_parameter)?;
+    let policy = ScoringPolicy::from_room(&room_results);
+    let method = TieBreakMethod::parse(&room_results.tie_break_method).unwrap_or(
+        TieBreakMethod::None
+    );
+
+    use crate::schema::rounds::dsl::*;
+    let round_results = rounds
+        .filter(crate::schema::rounds::dsl::room_id.eq(room_id_parameter))
+        .order(round_number.asc())
+        .load::<Round>(conn)?;
+
+    let participation_results: Vec<(Participation, Participant)> = Participation::belonging_to(
+        &round_results
+    )
+        .inner_join(crate::schema::participants::table)
+        .select((Participation::as_select(), Participant::as_select()))
+        .load::<(Participation, Participant)>(conn)?;
+
+    let participation_ids: Vec<String> = participation_results
+        .iter()
+        .map(|(participation, _)| participation.id.clone())
+        .collect();
+    let score_values = retrieve_score_values(conn, &participation_ids)?;
+
+    let round_numbers: HashMap<&str, i32> = round_results
+        .iter()
+        .map(|round| (round.id.as_str(), round.round_number))
+        .collect();
+
+    // Totals are summed in thousandths, like the scores themselves, and only
+    // become floats for ranking and in the response.
+    let mut items: Vec<(TieBreakInput, LeaderboardEntry)> = Vec::new();
+    let mut sums: Vec<FixedSums> = Vec::new();
+    let mut entry_positions: HashMap<String, usize> = HashMap::new();
+    for (participation, participant) in participation_results {
+        if !participation.competitive {
+            continue;
+        }
+        let deduction = to_fixed(participation.deduction.unwrap_or_default());
+        let fixed_net_score = participation.score.map(|score| i64::from(score) - deduction);
+        let response = ParticipationResponse::new(participation, participant);
+        let position = *entry_positions.entry(response.participant.id.clone()).or_insert_with(|| {
+            let input = TieBreakInput {
+                participant_id: response.participant.id.clone(),
+                ..Default::default()
+            };
+            items.push((input, LeaderboardEntry {
+                rank: None,
+                tied: false,
+                participant: response.participant.clone(),
+                rounds: Vec::new(),
+                total: 0,
+            }));
+            sums.push(FixedSums::default());
+            items.len() - 1
+        });
+        let (input, entry) = &mut items[position];
+        let sum = &mut sums[position];
+
+        if let Some(fixed_net_score) = fixed_net_score {
+            let values = score_values
+                .get(&response.participation.id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let (dropped_high, dropped_low) = dropped_scores(&policy, values);
+            sum.net_score = Some(sum.net_score.unwrap_or_default() + fixed_net_score);
+            if let Some(dropped_high) = dropped_high {
+                sum.dropped_high = Some(sum.dropped_high.unwrap_or_default() + to_fixed(dropped_high));
+            }
+            if let Some(dropped_low) = dropped_low {
+                sum.dropped_low = Some(sum.dropped_low.unwrap_or_default() + to_fixed(dropped_low));
+            }
+            input.net_score = sum.net_score.map(from_fixed);
+            input.dropped_high = sum.dropped_high.map(from_fixed);
+            input.dropped_low = sum.dropped_low.map(from_fixed);
+        }
+
+        entry.total = sum.net_score.unwrap_or_default();
+        entry.rounds.push(RoundScore {
+            round_number: round_numbers[response.participation.round_id.as_str()],
+            round_id: response.participation.round_id,
+            participation_id: response.participation.id,
+            net_score: response.net_score,
+        });
+    }
+
+    let (ranked, tie_breaks) = rank_tie_broken(
+        conn,
+        &room_results,
+        method,
+        room_id_parameter,
+        items,
+        coin_flips
+    )?;
+
+    let leaderboard = ranked
+        .into_iter()
+        .map(|ranked| {
+            let mut entry = ranked.item;
+            entry.rank = ranked.rank;
+            entry.tied = ranked.tied;
+            entry.rounds.sort_by_key(|round_score| round_score.round_number);
+            entry
+        })
+        .collect();
+
+    let results = LeaderboardResponse {
+        room: room_results,
+        rounds: round_results,
+        leaderboard,
+        tie_breaks,
+    };
+
+    return Ok(results);
+}
+
+/// A leaderboard entry's running sums, in thousandths.
+#[derive(Default)]
+struct FixedSums {
+    net_score: Option<i64>,
+    dropped_high: Option<i64>,
+    dropped_low: Option<i64>,
+}
+
+/// Whether ranking may settle a coin-flip tie that has no draw yet. Only host
+/// actions draw; reads report such ties as unresolved.
+/// `Draw` belongs inside a transaction; see `draw_coin_flips`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoinFlips {
+    Keep,
+    Draw,
+}
+
+fn rank_tie_broken<T>(
+    conn: &mut PgConnection,
+    room: &Room,
+    method: TieBreakMethod,
+    scope_id_parameter: &str,
+    mut items: Vec<(TieBreakInput, T)>,
+    coin_flips: CoinFlips
+) -> Result<(Vec<Ranked<T>>, Vec<TieBreakResolution>), AppError> {
+    let flip_groups = needs_coin_flip(method, &items);
+    if !flip_groups.is_empty() {
+        let draws = match coin_flips {
+            CoinFlips::Keep => retrieve_coin_flips(conn, scope_id_parameter, &flip_groups)?,
+            CoinFlips::Draw => draw_coin_flips(conn, &room.id, scope_id_parameter, &flip_groups)?,
+        };
+        for (input, _) in items.iter_mut() {
+            input.coin_flip = draws.get(&input.participant_id).copied();
+        }
+    }
+
+    return Ok(rank_with_tie_breaks(method, items));
+}
+
+/// Draws already recorded for the tied participants, so a standings page
+/// reloaded mid-bout always shows the same result.
+pub fn retrieve_coin_flips(
+    conn: &mut PgConnection,
+    scope_id_parameter: &str,
+    groups: &[Vec<String>]
+) -> Result<HashMap<String, i32>, AppError> {
+    use crate::schema::coin_flips::dsl::*;
+
+    let participant_ids: Vec<&String> = groups.iter().flatten().collect();
+    let results = coin_flips
+        .filter(scope_id.eq(scope_id_parameter))
+        .filter(participant_id.eq_any(participant_ids))
+        .load::<CoinFlip>(conn)?
+        .into_iter()
+        .map(|flip| (flip.participant_id, flip.draw))
+        .collect();
+
+    return Ok(results);
+}
+
+/// Settles every tie group that is missing a draw by shuffling it and handing
+/// out distinct draws, so the result is a fair and total order. The room row
+/// is locked so two hosts drawing at once can't both flip the same tie, which
+/// needs the caller to run this inside its transaction; the draws then commit
+/// or roll back with whatever the caller does with them.
+pub fn draw_coin_flips(
+    conn: &mut PgConnection,
+    room_id_parameter: &str,
+    scope_id_parameter: &str,
+    groups: &[Vec<String>]
+) -> Result<HashMap<String, i32>, AppError> {
+    use crate::schema::coin_flips::dsl::*;
+    use diesel::upsert::excluded;
+    use rand::seq::SliceRandom;
+
+    lock_room(conn, room_id_parameter)?;
+    let mut draws = retrieve_coin_flips(conn, scope_id_parameter, groups)?;
+
+    let mut new_flips: Vec<CoinFlip> = Vec::new();
+    for group in groups {
+        if group.iter().all(|participant_id_value| draws.contains_key(participant_id_value)) {
+            continue;
+        }
+        let mut shuffled = group.clone();
+        shuffled.shuffle(&mut rand::thread_rng());
+        for (position, participant_id_value) in shuffled.into_iter().enumerate() {
+            draws.insert(participant_id_value.clone(), position as i32);
+            new_flips.push(CoinFlip {
+                id: Uuid::new_v4().to_string(),
+                room_id: room_id_parameter.to_owned(),
+                scope_id: scope_id_parameter.to_owned(),
+                participant_id: participant_id_value,
+                draw: position as i32,
+                created: iso_date(),
+            });
+        }
+    }
+
+    if !new_flips.is_empty() {
+        diesel
+            ::insert_into(coin_flips)
+            .values(&new_flips)
+            .on_conflict((scope_id, participant_id))
+            .do_update()
+            .set((draw.eq(excluded(draw)), created.eq(excluded(created))))
+            .execute(conn)?;
+    }
+
+    return Ok(draws);
+}
+
+/// Individual judge scores per participation, sorted ascending.
+fn retrieve_score_values(
+    conn: &mut PgConnection,
+    participation_ids: &[String]
+) -> Result<HashMap<String, Vec<f32>>, AppError> {
+    use crate::schema::scores::dsl::*;
+
+    let score_results = scores
+        .filter(participation_id.eq_any(participation_ids))
+        .order(value.asc())
+        .load::<Score>(conn)?;
+
+    let mut results: HashMap<String, Vec<f32>> = HashMap::new();
+    for score in score_results {
+        results.entry(score.participation_id).or_default().push(from_fixed(i64::from(score.value)));
+    }
+
+    return Ok(results);
+}
+
+/// Picks who performs in the room's next round from its cut sizes. Round 1
+/// takes the whole room; later rounds take the top of the previous round.
+pub fn select_advancing_participants(
+    conn: &mut PgConnection,
+    room_id_parameter: &str
+) -> Result<Vec<Participant>, AppError> {
+    let room = find_room(conn, room_id_parameter)?;
+
+    use crate::schema::rounds::dsl::*;
+    let previous_round: Option<Round> = rounds
+        .filter(room_id.eq(room_id_parameter))
+        .order(round_number.desc())
+        .first(conn)
+        .optional()?;
+
+    let previous_round = match previous_round {
+        Some(previous_round) => previous_round,
+        None => {
+            let participants = retrieve_participants(conn, room.organization_id.as_deref(), room_id_parameter)?
+                .into_iter()
+                .filter(|participant| participant.competitive)
+                .collect();
+            return Ok(participants);
+        }
+    };
+
+    let next_round_number = previous_round.round_number + 1;
+    let size = match room.cut_sizes.get((next_round_number - 1) as usize) {
+        Some(size) => *size as usize,
+        None => {
+            return Err(
+                AppError::Conflict(
+                    format!("room has no cut size configured for round {}", next_round_number)
+                )
+            );
+        }
+    };
+
+    let standings = retrieve_round_standings(conn, &previous_round.id, CoinFlips::Draw)?.standings;
+    return select_cut(&standings, size);
+}
+
+/// Puts the next round's participants in performance order. Returns the seed
+/// used for a random draw so it can be recorded on the round.
+pub fn order_participants(
+    conn: &mut PgConnection,
+    room_id_parameter: &str,
+    participants: Vec<Participant>,
+    strategy: OrderStrategy,
+    seed: Option<i64>
+) -> Result<(Vec<Participant>, Option<i64>), AppError> {
+    match strategy {
+        OrderStrategy::Manual => {
+            return Ok((participants, None));
+        }
+        OrderStrategy::Random => {
+            let seed = seed.unwrap_or_else(|| Uuid::new_v4().as_u64_pair().0 as i64);
+            let mut participants = participants;
+            shuffle(&mut participants, seed);
+            return Ok((participants, Some(seed)));
+        }
+        OrderStrategy::Standings | OrderStrategy::ReverseStandings => {
+            use crate::schema::rounds::dsl::*;
+            let previous_round: Option<Round> = rounds
+                .filter(room_id.eq(room_id_parameter))
+                .order(round_number.desc())
+                .first(conn)
+                .optional()?;
+            let previous_round = match previous_round {
+                Some(previous_round) => previous_round,
+                None => {
+                    return Err(
+                        AppError::Conflict("the first round has no standings to order by".to_owned())
+                    );
+                }
+            };
+            let standings = retrieve_round_standings(
+                conn,
+                &previous_round.id,
+                CoinFlips::Draw
+            )?.standings;
+            let ordered = order_by_standings(
+                participants,
+                &standings,
+                strategy == OrderStrategy::ReverseStandings
+            );
+            return Ok((ordered, None));
+        }
+    }
+}
+
+pub fn create_next_round(
+    conn: &mut PgConnection,
+    room_id_parameter: &str,
+    participants: Vec<Participant>,
+    strategy: OrderStrategy,
+    seed: Option<i64>
+) -> Result<Round, AppError> {
+    return conn.transaction(|conn| {
+        let room = find_room(conn, room_id_parameter)?;
+        let room_participants = check_room_participants(
+            conn,
+            &room,
+            participants.iter().map(|participant| participant.id.as_str())
+        )?;
+
+        use crate::schema::rounds::dsl::*;
+
+        let previous_round_response: Option<Round> = rounds
+            .filter(room_id.eq(room_id_parameter))
+            .order(round_number.desc())
+            .first(conn)
+            .optional()?;
+
+        let mut new_round_number = 1;
+
+        if let Some(previous_round) = previous_round_response {
+            let prev_num = previous_round.round_number;
+            new_round_number += prev_num;
+        }
+
+        let new_round = Round {
+            id: Uuid::new_v4().to_string(),
+            room_id: room_id_parameter.to_owned(),
+            round_number: new_round_number,
+            order_strategy: strategy.as_str().to_owned(),
+            order_seed: seed,
+        };
+        // Two advances racing for the same round number: the loser rolls back here
+        // rather than leaving the room with duplicate rounds.
+        diesel
+            ::insert_into(rounds)
+            .values(&new_round)
+            .execute(conn)
+            .map_err(|error| {
+                match error {
+                    DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) =>
+                        AppError::Conflict(
+                            "room was advanced by another request; reload and try again".to_owned()
+                        ),
+                    error => AppError::from(error),
+                }
+            })?;
+
+        use crate::schema::rooms::dsl::*;
+        diesel
+            ::update(rooms)
+            .filter(crate::schema::rooms::id.eq(room_id_parameter))
+            .set((round_id_current.eq(&new_round.id), participation_id_current.eq(None::<String>)))
+            .execute(conn)?;
+
+        let mut vec: Vec<Participation> = Vec::new();
+        let parameter_round_id = &new_round.id;
+
+        for (pos, participant) in participants.iter().enumerate() {
+            let parameter_participant_id = &participant.id;
+
+            vec.push(Participation {
+                id: Uuid::new_v4().to_string(),
+                participant_id: parameter_participant_id.to_string(),
+                performance_order: pos as i32,
+                round_id: parameter_round_id.to_string(),
+                deduction: None,
+                performance_length_in_seconds: None,
+                performance_notes: None,
+                score: None,
+                competitive: room_participants[parameter_participant_id].competitive,
+                timer_started_at: None,
+                timer_stopped_at: None,
+            });
+        }
+
+        use crate::schema::participations::dsl::*;
+
+        diesel
+            ::insert_into(participations)
+            .values(vec)
+            .execute(conn)?;
+
+        Ok(new_round)
+    });
+}
+
+/// Participants of a room, within the caller's tenant.
+pub fn retrieve_participants(
+    conn: &mut PgConnection,
+    tenant: Option<&str>,
+    room_id_parameter: &str
+) -> Result<Vec<Participant>, AppError> {
+    use crate::schema::participants::dsl::*;
+
+    let results = participants
+        .filter(organization_id.is_not_distinct_from(tenant))
+        .filter(room_id.eq(room_id_parameter))
+        .load::<Participant>(conn)?;
+
+    return Ok(results);
+}
+
+/// Scores within the caller's tenant, which is the tenant of the participant
+/// each score is for.
+pub fn retrieve_scores(
+    conn: &mut PgConnection,
+    tenant: Option<&str>,
+    participation_id_parameter: &Option<String>,
+    submitter_id_parameter: &Option<String>
+) -> Result<Vec<Score>, AppError> {
+    use crate::schema::{ participants, participations };
+    use crate::schema::scores::dsl::*;
+
+    let tenant_participations = participations::table
+        .inner_join(participants::table)
+        .filter(participants::organization_id.is_not_distinct_from(tenant))
+        .select(participations::id);
+    let mut query = scores.filter(participation_id.eq_any(tenant_participations)).into_boxed();
+
+    if let Some(participation_id_parameter) = participation_id_parameter {
+        query = query.filter(participation_id.eq(participation_id_parameter));
+    }
+    
+    if let Some(submitter_id_parameter) = submitter_id_parameter {
+        query = query.filter(submitter_id.eq(submitter_id_parameter));
+    }
+
+    let results = query.load::<Score>(conn)?;
+
+    return Ok(results);
+}
+
+fn iso_date() -> String {
+    let now = SystemTime::now();
+    let now: DateTime<Utc> = now.into();
+    return now.to_rfc3339();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{ Arc, Once };
+    use super::*;
+
+    static MIGRATE: Once = Once::new();
+
+    fn migrate(conn: &mut PgConnection) {
+        MIGRATE.call_once(|| {
+            conn.run_pending_migrations(MIGRATIONS).expect("Error running migrations");
+        });
+    }
+
+    /// A connection to `DATABASE_URL` whose changes are rolled back when it is
+    /// dropped, so tests can run against a shared database.
+    fn connection() -> PgConnection {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let mut conn = PgConnection::establish(&database_url).expect("Error connecting to database");
+        migrate(&mut conn);
+        conn.begin_test_transaction().expect("Error starting test transaction");
+        return conn;
+    }
+
+    fn judge_tokens() -> Arc<JudgeTokens> {
+        return Arc::new(JudgeTokens::new(vec![7; MIN_JUDGE_TOKEN_SECRET_LENGTH], chrono::Duration::hours(1)));
+    }
+
+    fn bearer(token: &str) -> AccessToken {
+        return AccessToken::new(Some(token.to_owned()), judge_tokens());
+    }
+
+    fn policy(mode: AggregationMode, judge_count: i32, drop_count: i32) -> ScoringPolicy {
+        return ScoringPolicy { judge_count, drop_count, aggregation_mode: mode };
+    }
+
+    fn insert_test_room(
+        conn: &mut PgConnection,
+        policy: &ScoringPolicy,
+        method: TieBreakMethod,
+        cut_sizes_value: Vec<i32>
+    ) -> Room {
+        let range = ScoreRange {
+            min: DEFAULT_SCORE_MIN,
+            max: DEFAULT_SCORE_MAX,
+            precision: DEFAULT_SCORE_PRECISION,
+        };
+        let creator = test_member(conn);
+        return insert_room(conn, "Test slam", policy, method, cut_sizes_value, &range, &creator).unwrap();
+    }
+
+    /// An organizer who can't sign in; Argon2 is too slow in debug builds to
+    /// hash a password for every test room.
+    fn test_organizer(conn: &mut PgConnection) -> Organizer {
+        let email_value = format!("{}@example.com", Uuid::new_v4().simple());
+        return insert_organizer(conn, &email_value, "Test organizer", "no password".to_owned()).unwrap();
+    }
+
+    /// The owner of a fresh organization.
+    fn test_member(conn: &mut PgConnection) -> OrganizationMember {
+        let organizer = test_organizer(conn);
+        let organization = insert_organization(conn, "Test organization", &organizer.id).unwrap();
+        return find_member(conn, &organization.organization.id, &organizer.id).unwrap().unwrap();
+    }
+
+    /// A room with every judge seat filled.
+    fn test_room(conn: &mut PgConnection, policy: &ScoringPolicy) -> Room {
+        let room = insert_test_room(conn, policy, TieBreakMethod::HighLowCoin, Vec::new());
+        seat_judges(conn, &room);
+        return room;
+    }
+
+    fn seat_judges(conn: &mut PgConnection, room: &Room) {
+        for _ in 0..room.judge_count {
+            insert_judge(conn, &room.id, None, None).unwrap();
+        }
+    }
+
+    /// Scores a participation as the judge in `seat_value` of its room.
+    fn submit_score(
+        conn: &mut PgConnection,
+        participation_id_value: &str,
+        seat_value: i32,
+        value_value: f32
+    ) -> Result<Score, AppError> {
+        let room = retrieve_participation_room(conn, participation_id_value).unwrap();
+        let judge: Judge = crate::schema::judges::table
+            .filter(crate::schema::judges::room_id.eq(&room.id))
+            .filter(crate::schema::judges::seat.eq(seat_value))
+            .first(conn)
+            .unwrap();
+        return insert_score(conn, &value_value, participation_id_value, &judge.id);
+    }
+
+    /// A room whose first round has a single poet in it.
+    fn seeded_room(conn: &mut PgConnection, policy: &ScoringPolicy) -> (Room, Participation) {
+        let room = test_room(conn, policy);
+        let participant = insert_participant(conn, "Test poet", None, &room.id, true).unwrap();
+        let round = create_next_round(
+            conn,
+            &room.id,
+            vec![participant],
+            OrderStrategy::Manual,
+            None
+        ).unwrap();
+        let participation = crate::schema::participations::table
+            .filter(crate::schema::participations::round_id.eq(&round.id))
+            .first(conn)
+            .unwrap();
+        return (room, participation);
+    }
+
+    /// A room whose first round has the named poets in it, in that order.
+    fn seeded_round(
+        conn: &mut PgConnection,
+        policy: &ScoringPolicy,
+        names: &[&str]
+    ) -> (Room, Vec<ParticipationResponse>) {
+        let room = test_room(conn, policy);
+        let participants = names
+            .iter()
+            .map(|name_value| insert_participant(conn, name_value, None, &room.id, true).unwrap())
+            .collect();
+        let round = create_next_round(conn, &room.id, participants, OrderStrategy::Manual, None).unwrap();
+        return (room, retrieve_round(conn, &round.id).unwrap().participations);
+    }
+
+    fn score_all(conn: &mut PgConnection, participation_id_value: &str, values: &[f32]) {
+        for (pos, value_value) in values.iter().enumerate() {
+            submit_score(conn, participation_id_value, (pos as i32) + 1, *value_value).unwrap();
+        }
+    }
+
+    fn load_participation(conn: &mut PgConnection, participation_id_value: &str) -> Participation {
+        return crate::schema::participations::table.find(participation_id_value).first(conn).unwrap();
+    }
+
+    fn stored_score(conn: &mut PgConnection, participation_id_value: &str) -> Option<i32> {
+        return load_participation(conn, participation_id_value).score;
+    }
+
+    #[test]
+    fn score_is_aggregated_once_every_judge_has_scored() {
+        let conn = &mut connection();
+        let (_, participation) = seeded_room(conn, &policy(AggregationMode::TrimmedSum, 3, 1));
+
+        submit_score(conn, &participation.id, 1, 8.0).unwrap();
+        submit_score(conn, &participation.id, 2, 9.5).unwrap();
+        assert_eq!(stored_score(conn, &participation.id), None);
+
+        submit_score(conn, &participation.id, 3, 7.0).unwrap();
+        assert_eq!(stored_score(conn, &participation.id), Some(8000));
+    }
+
+    #[test]
+    fn score_uses_the_rooms_aggregation_mode() {
+        let conn = &mut connection();
+        let (_, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+
+        submit_score(conn, &participation.id, 1, 8.0).unwrap();
+        submit_score(conn, &participation.id, 2, 9.5).unwrap();
+        assert_eq!(stored_score(conn, &participation.id), Some(17500));
+    }
+
+    #[test]
+    fn rescoring_replaces_the_judges_earlier_score() {
+        let conn = &mut connection();
+        let (room, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+
+        submit_score(conn, &participation.id, 1, 8.0).unwrap();
+        submit_score(conn, &participation.id, 2, 9.5).unwrap();
+        submit_score(conn, &participation.id, 1, 9.0).unwrap();
+        let tenant = room.organization_id.as_deref();
+        assert_eq!(retrieve_scores(conn, tenant, &Some(participation.id.clone()), &None).unwrap().len(), 2);
+        assert_eq!(stored_score(conn, &participation.id), Some(18500));
+    }
+
+    #[test]
+    fn deduction_follows_the_room_time_penalty() {
+        let conn = &mut connection();
+        let (room, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+
+        update_participation(conn, participation.id.clone(), None, Some(211), None).unwrap();
+        let stored = load_participation(conn, &participation.id);
+        assert_eq!(stored.deduction, Some(1.0));
+
+        let strict = TimePenaltySettings { grace_seconds: 0, ..TimePenaltySettings::default() };
+        upsert_time_penalty(conn, &room.id, None, &strict).unwrap();
+        let stored = load_participation(conn, &participation.id);
+        assert_eq!(stored.deduction, Some(1.5));
+    }
+
+    #[test]
+    fn round_time_penalty_overrides_the_room_until_removed() {
+        let conn = &mut connection();
+        let (room, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        update_participation(conn, participation.id.clone(), None, Some(211), None).unwrap();
+
+        let waived = TimePenaltySettings { no_penalty: true, ..TimePenaltySettings::default() };
+        upsert_time_penalty(conn, &room.id, Some(&participation.round_id), &waived).unwrap();
+        assert!(retrieve_time_penalty(conn, &room.id, Some(&participation.round_id)).unwrap().no_penalty);
+        assert!(!retrieve_time_penalty(conn, &room.id, None).unwrap().no_penalty);
+        let stored = load_participation(conn, &participation.id);
+        assert_eq!(stored.deduction, Some(0.0));
+
+        remove_round_time_penalty(conn, &participation.round_id).unwrap();
+        let stored = load_participation(conn, &participation.id);
+        assert_eq!(stored.deduction, Some(1.0));
+    }
+
+    #[test]
+    fn round_shows_raw_deduction_and_net_scores() {
+        let conn = &mut connection();
+        let (_, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        update_participation(conn, participation.id.clone(), None, Some(211), None).unwrap();
+
+        let round = retrieve_round(conn, &participation.round_id).unwrap();
+        assert_eq!(round.participations[0].raw_score, None);
+        assert_eq!(round.participations[0].deduction, 1.0);
+        assert_eq!(round.participations[0].net_score, None);
+
+        submit_score(conn, &participation.id, 1, 8.0).unwrap();
+        submit_score(conn, &participation.id, 2, 9.5).unwrap();
+        let round = retrieve_round(conn, &participation.round_id).unwrap();
+        assert_eq!(round.participations[0].raw_score, Some(17.5));
+        assert_eq!(round.participations[0].net_score, Some(16.5));
+    }
+
+    #[test]
+    fn leaderboard_totals_net_scores_across_rounds() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let first = insert_participant(conn, "First poet", None, &room.id, true).unwrap();
+        let second = insert_participant(conn, "Second poet", None, &room.id, true).unwrap();
+        let third = insert_participant(conn, "Third poet", None, &room.id, true).unwrap();
+
+        for value_value in [8.0, 9.0] {
+            let round = create_next_round(
+                conn,
+                &room.id,
+                vec![first.clone(), second.clone(), third.clone()],
+                OrderStrategy::Manual,
+                None
+            ).unwrap();
+            let round = retrieve_round(conn, &round.id).unwrap();
+            for response in &round.participations {
+                let bonus = if response.participant.id == second.id { 0.5 } else { 0.0 };
+                if response.participant.id != third.id {
+                    submit_score(conn, &response.participation.id, 1, value_value + bonus).unwrap();
+                }
+            }
+        }
+
+        let leaderboard = retrieve_room_leaderboard(conn, &room.id, CoinFlips::Keep).unwrap().leaderboard;
+        let totals: Vec<(&str, Option<i32>, i64)> = leaderboard
+            .iter()
+            .map(|entry| (entry.participant.name.as_str(), entry.rank, entry.total))
+            .collect();
+        assert_eq!(
+            totals,
+            vec![("Second poet", Some(1), 18000), ("First poet", Some(2), 17000), ("Third poet", None, 0)]
+        );
+        assert_eq!(leaderboard[0].rounds.len(), 2);
+    }
+
+    #[test]
+    fn round_standings_rank_by_net_score() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let first = insert_participant(conn, "First poet", None, &room.id, true).unwrap();
+        let second = insert_participant(conn, "Second poet", None, &room.id, true).unwrap();
+        let round = create_next_round(
+            conn,
+            &room.id,
+            vec![first, second.clone()],
+            OrderStrategy::Manual,
+            None
+        ).unwrap();
+
+        for response in retrieve_round(conn, &round.id).unwrap().participations {
+            submit_score(conn, &response.participation.id, 1, 9.0).unwrap();
+            if response.participant.id == second.id {
+                update_participation(conn, response.participation.id.clone(), None, Some(200), None).unwrap();
+            }
+        }
+
+        let standings = retrieve_round_standings(conn, &round.id, CoinFlips::Keep).unwrap().standings;
+        assert_eq!(standings[0].participation.participant.name, "First poet");
+        assert_eq!(standings[1].participation.net_score, Some(8.5));
+        assert_eq!(standings[1].rank, Some(2));
+    }
+
+    #[test]
+    fn standings_break_ties_on_the_dropped_high_score() {
+        let conn = &mut connection();
+        let (_, poets) = seeded_round(conn, &policy(AggregationMode::TrimmedSum, 3, 1), &["A", "B"]);
+        score_all(conn, &poets[0].participation.id, &[7.0, 8.0, 9.0]);
+        score_all(conn, &poets[1].participation.id, &[6.0, 8.0, 10.0]);
+
+        let standings = retrieve_round_standings(conn, &poets[0].participation.round_id, CoinFlips::Keep).unwrap();
+        let order: Vec<(&str, Option<i32>)> = standings.standings
+            .iter()
+            .map(|standing| (standing.participation.participant.name.as_str(), standing.rank))
+            .collect();
+        assert_eq!(order, vec![("B", Some(1)), ("A", Some(2))]);
+        assert_eq!(standings.tie_breaks.len(), 1);
+        assert!(standings.tie_breaks[0].resolved);
+    }
+
+    #[test]
+    fn coin_flips_are_drawn_once_by_the_host_and_kept() {
+        let conn = &mut connection();
+        let (_, poets) = seeded_round(conn, &policy(AggregationMode::TrimmedSum, 3, 1), &["A", "B"]);
+        score_all(conn, &poets[0].participation.id, &[7.0, 8.0, 9.0]);
+        score_all(conn, &poets[1].participation.id, &[7.0, 8.0, 9.0]);
+
+        let round_id_value = &poets[0].participation.round_id;
+        let read = retrieve_round_standings(conn, round_id_value, CoinFlips::Keep).unwrap();
+        assert!(!read.tie_breaks[0].resolved);
+        assert_eq!(read.standings[1].rank, Some(1));
+
+        let first = retrieve_round_standings(conn, round_id_value, CoinFlips::Draw).unwrap();
+        let second = retrieve_round_standings(conn, round_id_value, CoinFlips::Draw).unwrap();
+        let reread = retrieve_round_standings(conn, round_id_value, CoinFlips::Keep).unwrap();
+        assert!(first.tie_breaks[0].resolved);
+        assert!(reread.tie_breaks[0].resolved);
+        assert_eq!(first.standings[0].rank, Some(1));
+        assert_eq!(first.standings[1].rank, Some(2));
+        assert_eq!(
+            first.standings[0].participation.participant.id,
+            second.standings[0].participation.participant.id
+        );
+        assert_eq!(
+            first.standings[0].participation.participant.id,
+            reread.standings[0].participation.participant.id
+        );
+
+        let flips: i64 = crate::schema::coin_flips::table
+            .filter(crate::schema::coin_flips::scope_id.eq(round_id_value))
+            .count()
+            .get_result(conn)
+            .unwrap();
+        assert_eq!(flips, 2);
+    }
+
+    fn names(participants: &[Participant]) -> Vec<&str> {
+        return participants
+            .iter()
+            .map(|participant| participant.name.as_str())
+            .collect();
+    }
+
+    #[test]
+    fn cut_advances_the_top_of_the_previous_round() {
+        let conn = &mut connection();
+        let sum = policy(AggregationMode::Sum, 1, 0);
+        let room = insert_test_room(conn, &sum, TieBreakMethod::None, vec![3, 2]);
+        seat_judges(conn, &room);
+        for name_value in ["A", "B", "C"] {
+            insert_participant(conn, name_value, None, &room.id, true).unwrap();
+        }
+
+        let first_field = select_advancing_participants(conn, &room.id).unwrap();
+        assert_eq!(first_field.len(), 3);
+        let round = create_next_round(conn, &room.id, first_field, OrderStrategy::Manual, None).unwrap();
+        assert!(select_advancing_participants(conn, &room.id).is_err());
+
+        for (pos, response) in retrieve_round(conn, &round.id).unwrap().participations.iter().enumerate() {
+            submit_score(conn, &response.participation.id, 1, 7.0 + (pos as f32)).unwrap();
+        }
+        let second_field = select_advancing_participants(conn, &room.id).unwrap();
+        assert_eq!(names(&second_field), vec!["C", "B"]);
+
+        create_next_round(conn, &room.id, second_field, OrderStrategy::Manual, None).unwrap();
+        let error = select_advancing_participants(conn, &room.id).unwrap_err();
+        assert!(matches!(error, AppError::Conflict(message) if message.contains("round 3")));
+    }
+
+    #[test]
+    fn cut_refuses_a_tie_the_room_cannot_break() {
+        let conn = &mut connection();
+        let sum = policy(AggregationMode::Sum, 1, 0);
+        let room = insert_test_room(conn, &sum, TieBreakMethod::None, vec![3, 1]);
+        seat_judges(conn, &room);
+        for name_value in ["A", "B", "C"] {
+            insert_participant(conn, name_value, None, &room.id, true).unwrap();
+        }
+        let first_field = select_advancing_participants(conn, &room.id).unwrap();
+        let round = create_next_round(conn, &room.id, first_field, OrderStrategy::Manual, None).unwrap();
+        for response in retrieve_round(conn, &round.id).unwrap().participations {
+            submit_score(conn, &response.participation.id, 1, 8.0).unwrap();
+        }
+
+        let error = select_advancing_participants(conn, &room.id).unwrap_err();
+        assert!(matches!(error, AppError::Conflict(message) if message.contains("tied at the cut line")));
+    }
+
+    #[test]
+    fn random_order_is_recorded_and_replayable() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let participants: Vec<Participant> = (0..8)
+            .map(|pos| insert_participant(conn, &format!("Poet {}", pos), None, &room.id, true).unwrap())
+            .collect();
+
+        let (drawn, seed) = order_participants(
+            conn,
+            &room.id,
+            participants.clone(),
+            OrderStrategy::Random,
+            None
+        ).unwrap();
+        let (replayed, _) = order_participants(
+            conn,
+            &room.id,
+            participants,
+            OrderStrategy::Random,
+            seed
+        ).unwrap();
+        assert_eq!(names(&drawn), names(&replayed));
+
+        let round = create_next_round(conn, &room.id, drawn, OrderStrategy::Random, seed).unwrap();
+        let stored: Round = crate::schema::rounds::table.find(&round.id).first(conn).unwrap();
+        assert_eq!(stored.order_strategy, "random");
+        assert_eq!(stored.order_seed, seed);
+    }
+
+    #[test]
+    fn standings_order_follows_the_previous_round() {
+        let conn = &mut connection();
+        let (room, poets) = seeded_round(conn, &policy(AggregationMode::Sum, 1, 0), &["A", "B", "C"]);
+        let participants: Vec<Participant> = poets
+            .iter()
+            .map(|response| response.participant.clone())
+            .collect();
+        let unplayed = participants.clone();
+        assert!(order_participants(conn, "no-such-room", unplayed, OrderStrategy::Standings, None).is_err());
+
+        for (pos, response) in poets.iter().enumerate() {
+            submit_score(conn, &response.participation.id, 1, 7.0 + (pos as f32)).unwrap();
+        }
+        let (best_first, _) = order_participants(
+            conn,
+            &room.id,
+            participants.clone(),
+            OrderStrategy::Standings,
+            None
+        ).unwrap();
+        assert_eq!(names(&best_first), vec!["C", "B", "A"]);
+
+        let (worst_first, _) = order_participants(
+            conn,
+            &room.id,
+            participants,
+            OrderStrategy::ReverseStandings,
+            None
+        ).unwrap();
+        assert_eq!(names(&worst_first), vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn sacrificial_poet_is_scored_but_not_ranked() {
+        let conn = &mut connection();
+        let (room, poets) = seeded_round(conn, &policy(AggregationMode::Sum, 1, 0), &["A"]);
+        let round_id_value = &poets[0].participation.round_id;
+        let sacrifice = insert_participant(conn, "Sacrifice", None, &room.id, false).unwrap();
+        let calibration = insert_participation(conn, round_id_value, &sacrifice.id, None, Some(0)).unwrap();
+
+        submit_score(conn, &calibration.id, 1, 9.5).unwrap();
+        submit_score(conn, &poets[0].participation.id, 1, 8.0).unwrap();
+        assert_eq!(stored_score(conn, &calibration.id), Some(9500));
+
+        let standings = retrieve_round_standings(conn, round_id_value, CoinFlips::Keep).unwrap().standings;
+        assert_eq!(standings.len(), 1);
+        assert_eq!(standings[0].participation.participant.name, "A");
+        assert_eq!(standings[0].rank, Some(1));
+
+        let leaderboard = retrieve_room_leaderboard(conn, &room.id, CoinFlips::Keep).unwrap().leaderboard;
+        assert_eq!(leaderboard.len(), 1);
+    }
+
+    #[test]
+    fn inserted_participation_shifts_later_performers_back() {
+        let conn = &mut connection();
+        let (room, poets) = seeded_round(conn, &policy(AggregationMode::Sum, 1, 0), &["A", "B"]);
+        let round_id_value = &poets[0].participation.round_id;
+        let late = insert_participant(conn, "Late", None, &room.id, true).unwrap();
+        let sacrifice = insert_participant(conn, "Sacrifice", None, &room.id, false).unwrap();
+
+        insert_participation(conn, round_id_value, &late.id, None, None).unwrap();
+        insert_participation(conn, round_id_value, &sacrifice.id, None, Some(0)).unwrap();
+
+        let order: Vec<(String, i32)> = retrieve_round(conn, round_id_value).unwrap().participations
+            .into_iter()
+            .map(|response| (response.participant.name, response.participation.performance_order))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("Sacrifice".to_owned(), 0),
+                ("A".to_owned(), 1),
+                ("B".to_owned(), 2),
+                ("Late".to_owned(), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn judges_take_the_lowest_free_seat_up_to_the_judge_count() {
+        let conn = &mut connection();
+        let sum = policy(AggregationMode::Sum, 3, 0);
+        let room = insert_test_room(conn, &sum, TieBreakMethod::None, Vec::new());
+
+        let second = insert_judge(conn, &room.id, Some(2), Some("Guest".to_owned())).unwrap();
+        assert_eq!((second.seat, second.label.as_str()), (2, "Guest"));
+        let first = insert_judge(conn, &room.id, None, None).unwrap();
+        assert_eq!((first.seat, first.label.as_str()), (1, "Judge A"));
+
+        assert!(insert_judge(conn, &room.id, Some(2), None).is_err());
+        assert!(insert_judge(conn, &room.id, Some(4), None).is_err());
+        assert!(insert_judge(conn, &room.id, Some(0), None).is_err());
+        assert_eq!(insert_judge(conn, &room.id, None, None).unwrap().seat, 3);
+        assert!(insert_judge(conn, &room.id, None, None).is_err());
+
+        let seats: Vec<i32> = retrieve_judges(conn, room.organization_id.as_deref(), &room.id).unwrap()
+            .iter()
+            .map(|judge| judge.seat)
+            .collect();
+        assert_eq!(seats, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn only_judges_seated_in_the_room_may_score() {
+        let conn = &mut connection();
+        let (_, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        let other_room = test_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        let outsider = retrieve_judges(conn, other_room.organization_id.as_deref(), &other_room.id).unwrap().remove(0);
+
+        assert!(matches!(
+            insert_score(conn, &8.0, &participation.id, "not-a-judge"),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(matches!(
+            insert_score(conn, &8.0, &participation.id, &outsider.id),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(submit_score(conn, &participation.id, 1, 8.0).is_ok());
+    }
+
+    #[test]
+    fn scores_are_checked_against_the_room_range() {
+        let conn = &mut connection();
+        let (_, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+
+        let rejection = submit_score(conn, &participation.id, 1, 10.5).unwrap_err();
+        assert!(matches!(
+            rejection,
+            AppError::Validation { details: Some(details), .. } if details["field"] == "value"
+        ));
+        assert!(submit_score(conn, &participation.id, 1, 8.25).is_err());
+
+        let stored = submit_score(conn, &participation.id, 1, 8.299999).unwrap();
+        assert_eq!(stored.value, 8300);
+        submit_score(conn, &participation.id, 2, 8.1).unwrap();
+        assert_eq!(stored_score(conn, &participation.id), Some(16400));
+    }
+
+    #[tokio::test]
+    async fn pooled_queries_return_their_result_or_error() {
+        let pool = establish_pool();
+        migrate(&mut pool.get().unwrap());
+
+        let missing = with_connection(&pool, |conn| find_room(conn, "no-such-room")).await;
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+
+        let count = with_connection(&pool, |conn| {
+            return Ok(crate::schema::rooms::table.count().get_result::<i64>(conn)?);
+        }).await;
+        assert!(count.is_ok());
+    }
+
+    #[test]
+    fn round_numbers_are_unique_within_a_room() {
+        let conn = &mut connection();
+        let (room, poets) = seeded_round(conn, &policy(AggregationMode::Sum, 1, 0), &["A"]);
+        let duplicate = Round {
+            id: Uuid::new_v4().to_string(),
+            round_number: 1,
+            room_id: room.id.clone(),
+            order_strategy: OrderStrategy::Manual.as_str().to_owned(),
+            order_seed: None,
+        };
+        let result = conn.transaction(|conn| {
+            return diesel::insert_into(crate::schema::rounds::table).values(&duplicate).execute(conn);
+        });
+        assert!(matches!(result, Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _))));
+
+        let second = create_next_round(conn, &room.id, Vec::new(), OrderStrategy::Manual, None).unwrap();
+        assert_eq!(second.round_number, 2);
+        assert_ne!(second.id, poets[0].participation.round_id);
+    }
+
+    #[test]
+    fn failed_round_creation_leaves_the_room_untouched() {
+        let conn = &mut connection();
+        let (room, poets) = seeded_round(conn, &policy(AggregationMode::Sum, 1, 0), &["A"]);
+        let ghost = Participant {
+            id: Uuid::new_v4().to_string(),
+            name: "Ghost".to_owned(),
+            pronouns: None,
+            room_id: room.id.clone(),
+            organization_id: room.organization_id.clone(),
+            competitive: true,
+        };
+
+        assert!(create_next_round(conn, &room.id, vec![ghost], OrderStrategy::Manual, None).is_err());
+        let room = find_room(conn, &room.id).unwrap();
+        assert_eq!(room.round_id_current, Some(poets[0].participation.round_id.clone()));
+        let round_count: i64 = crate::schema::rounds::table
+            .filter(crate::schema::rounds::room_id.eq(&room.id))
+            .count()
+            .get_result(conn)
+            .unwrap();
+        assert_eq!(round_count, 1);
+    }
+
+    #[test]
+    fn a_judge_has_one_score_row_per_participation() {
+        let conn = &mut connection();
+        let (_, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        let first = submit_score(conn, &participation.id, 1, 8.0).unwrap();
+        let duplicate = Score {
+            id: Uuid::new_v4().to_string(),
+            value: 9000,
+            submitter_id: first.submitter_id.clone(),
+            participation_id: participation.id.clone(),
+        };
+        let result = conn.transaction(|conn| {
+            return diesel::insert_into(crate::schema::scores::table).values(&duplicate).execute(conn);
+        });
+        assert!(matches!(result, Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _))));
+
+        let rescored = submit_score(conn, &participation.id, 1, 9.0).unwrap();
+        assert_eq!(rescored.id, first.id);
+        assert_eq!(rescored.value, 9000);
+    }
+
+    #[test]
+    fn updates_and_deletes_return_the_row_they_touched() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let poet = insert_participant(conn, "Test poet", None, &room.id, true).unwrap();
+
+        let renamed = update_participant(conn, poet.id.clone(), Some("Renamed".to_owned()), None, None).unwrap();
+        assert_eq!(renamed.name, "Renamed");
+        assert_eq!(remove_participant(conn, poet.id.clone()).unwrap().id, poet.id);
+        assert!(matches!(remove_participant(conn, poet.id.clone()), Err(AppError::NotFound(_))));
+        assert!(matches!(remove_judge(conn, "no-such-judge".to_owned()), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn performer_steps_through_the_running_order() {
+        let conn = &mut connection();
+        let (room, poets) = seeded_round(conn, &policy(AggregationMode::Sum, 1, 0), &["A", "B"]);
+        let on_stage = |current: CurrentRoundResponse| {
+            return current.current_participation.map(|participation| participation.participant.name);
+        };
+
+        assert!(matches!(
+            step_current_participation(conn, &room.id, PerformerStep::Previous),
+            Err(AppError::Conflict(_))
+        ));
+        let current = step_current_participation(conn, &room.id, PerformerStep::Next).unwrap();
+        assert_eq!(on_stage(current), Some("A".to_owned()));
+        let current = step_current_participation(conn, &room.id, PerformerStep::Next).unwrap();
+        assert_eq!(on_stage(current), Some("B".to_owned()));
+        assert!(step_current_participation(conn, &room.id, PerformerStep::Next).is_err());
+        let current = step_current_participation(conn, &room.id, PerformerStep::Previous).unwrap();
+        assert_eq!(on_stage(current), Some("A".to_owned()));
+
+        let current = update_current_participation(conn, &room.id, Some(&poets[1].participation.id)).unwrap();
+        assert_eq!(on_stage(current), Some("B".to_owned()));
+        let current = update_current_participation(conn, &room.id, None).unwrap();
+        assert_eq!(on_stage(current), None);
+    }
+
+    #[test]
+    fn performer_must_be_in_the_current_round() {
+        let conn = &mut connection();
+        let (room, poets) = seeded_round(conn, &policy(AggregationMode::Sum, 1, 0), &["A"]);
+        update_current_participation(conn, &room.id, Some(&poets[0].participation.id)).unwrap();
+        create_next_round(conn, &room.id, Vec::new(), OrderStrategy::Manual, None).unwrap();
+
+        assert!(retrieve_current_round(conn, &room.id).unwrap().current_participation.is_none());
+        assert!(matches!(
+            update_current_participation(conn, &room.id, Some(&poets[0].participation.id)),
+            Err(AppError::Validation { .. })
+        ));
+    }
+
+    #[test]
+    fn timer_records_the_length_and_deduction_until_reset() {
+        let conn = &mut connection();
+        let (_, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 1, 0));
+
+        assert!(matches!(stop_timer(conn, &participation.id), Err(AppError::Conflict(_))));
+        let started = start_timer(conn, &participation.id).unwrap();
+        assert!(started.timer_started_at.is_some());
+        assert!(matches!(start_timer(conn, &participation.id), Err(AppError::Conflict(_))));
+
+        let stopped = stop_timer(conn, &participation.id).unwrap();
+        assert!(stopped.timer_stopped_at.is_some());
+        assert_eq!(stopped.performance_length_in_seconds, Some(0));
+        assert_eq!(stopped.deduction, Some(0.0));
+        assert!(start_timer(conn, &participation.id).is_err());
+
+        let reset = reset_timer(conn, &participation.id).unwrap();
+        assert_eq!((reset.timer_started_at, reset.performance_length_in_seconds), (None, None));
+        assert!(start_timer(conn, &participation.id).is_ok());
+    }
+
+    #[test]
+    fn credentials_only_open_their_own_room() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let other = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let host = insert_credential(conn, &room.id, Role::Host, None, None).unwrap();
+        let audience = insert_credential(conn, &room.id, Role::Audience, None, None).unwrap();
+
+        assert!(authorize(conn, &bearer(&host.token), &room.id, HOST).is_ok());
+        assert!(matches!(authorize(conn, &bearer(&audience.token), &room.id, HOST), Err(AppError::Forbidden(_))));
+        assert!(matches!(authorize(conn, &bearer(&host.token), &other.id, HOST), Err(AppError::Forbidden(_))));
+        assert!(matches!(authorize(conn, &bearer("made-up"), &room.id, EVERYONE), Err(AppError::Unauthorized(_))));
+        assert!(matches!(authorize(conn, &AccessToken::new(None, judge_tokens()), &room.id, EVERYONE), Err(AppError::Unauthorized(_))));
+
+        let visible: Vec<String> = retrieve_rooms(conn, room.organization_id.as_deref(), &[room.id.clone(), "unknown".to_owned()])
+            .unwrap()
+            .into_iter()
+            .map(|room| room.id)
+            .collect();
+        assert_eq!(visible, vec![room.id.clone()]);
+    }
+
+    #[test]
+    fn judge_credentials_need_a_judge_seated_in_the_room() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let other = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let judge = retrieve_judges(conn, room.organization_id.as_deref(), &room.id).unwrap().remove(0);
+        let outsider = retrieve_judges(conn, other.organization_id.as_deref(), &other.id).unwrap().remove(0);
+
+        assert!(insert_credential(conn, &room.id, Role::Judge, None, None).is_err());
+        assert!(insert_credential(conn, &room.id, Role::Judge, Some(outsider.id), None).is_err());
+        assert!(insert_credential(conn, &room.id, Role::Host, Some(judge.id.clone()), None).is_err());
+        let credential = insert_credential(conn, &room.id, Role::Judge, Some(judge.id.clone()), None).unwrap();
+        assert_eq!(credential.credential.judge_id, Some(judge.id));
+    }
+
+    #[test]
+    fn a_room_keeps_its_last_host_credential() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let first = insert_credential(conn, &room.id, Role::Host, None, None).unwrap();
+        let second = insert_credential(conn, &room.id, Role::Host, None, None).unwrap();
+
+        remove_credential(conn, first.credential.id.clone()).unwrap();
+        assert!(authorize(conn, &bearer(&first.token), &room.id, HOST).is_err());
+        assert!(matches!(remove_credential(conn, second.credential.id.clone()), Err(AppError::Conflict(_))));
+        assert_eq!(retrieve_credentials(conn, &room.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn join_code_and_seat_buy_a_judge_token() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        let other = test_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        assert_ne!(room.join_code, other.join_code);
+
+        let issued = issue_judge_token(conn, &judge_tokens(), &room.join_code.to_lowercase(), 2).unwrap();
+        assert_eq!((issued.room_id.clone(), issued.judge.seat), (room.id.clone(), 2));
+        let caller = authorize(conn, &bearer(&issued.token), &room.id, SCORERS).unwrap();
+        assert_eq!(caller.judge_id, Some(issued.judge.id.clone()));
+        assert!(matches!(authorize(conn, &bearer(&issued.token), &other.id, SCORERS), Err(AppError::Forbidden(_))));
+        assert!(matches!(issue_judge_token(conn, &judge_tokens(), &room.join_code, 3), Err(AppError::NotFound(_))));
+        assert!(matches!(issue_judge_token(conn, &judge_tokens(), "ZZZZZZ", 1), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn new_join_code_or_removed_seat_revokes_judge_tokens() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        let first = issue_judge_token(conn, &judge_tokens(), &room.join_code, 1).unwrap();
+        let second = issue_judge_token(conn, &judge_tokens(), &room.join_code, 2).unwrap();
+
+        remove_judge(conn, second.judge.id.clone()).unwrap();
+        assert!(matches!(authenticate(conn, &bearer(&second.token)), Err(AppError::Unauthorized(_))));
+
+        let rotated = update_join_code(conn, &room.id).unwrap();
+        assert_ne!(rotated.join_code, room.join_code);
+        assert!(matches!(authenticate(conn, &bearer(&first.token)), Err(AppError::Unauthorized(_))));
+        let rejoined = issue_judge_token(conn, &judge_tokens(), &rotated.join_code, 1).unwrap();
+        assert!(authenticate(conn, &bearer(&rejoined.token)).is_ok());
+    }
+
+    #[test]
+    fn organizers_sign_in_and_host_only_their_own_rooms() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let organizer: Organizer = diesel
+            ::update(crate::schema::organizers::table.find(room.organizer_id.clone().unwrap()))
+            .set(crate::schema::organizers::password_hash.eq(hash_password("correct horse").unwrap()))
+            .get_result(conn)
+            .unwrap();
+        let stranger = test_organizer(conn);
+
+        assert!(matches!(sign_in(conn, &organizer.email, "wrong password"), Err(AppError::Unauthorized(_))));
+        assert!(matches!(sign_in(conn, "nobody@example.com", "correct horse"), Err(AppError::Unauthorized(_))));
+        let session = sign_in(conn, &organizer.email, "correct horse").unwrap();
+        let caller = authorize(conn, &bearer(&session.token), &room.id, HOST).unwrap();
+        assert_eq!(caller.role, Role::Host);
+        assert_eq!(retrieve_member_rooms(conn, &organizer.id, None).unwrap().len(), 1);
+
+        let other_session = insert_session(conn, stranger.clone()).unwrap();
+        assert!(matches!(authorize(conn, &bearer(&other_session.token), &room.id, EVERYONE), Err(AppError::Forbidden(_))));
+        assert!(retrieve_member_rooms(conn, &stranger.id, None).unwrap().is_empty());
+        assert!(matches!(
+            insert_organizer(conn, &organizer.email, "Copy", "hash".to_owned()),
+            Err(AppError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn signed_out_and_expired_sessions_are_refused() {
+        let conn = &mut connection();
+        let organizer = test_organizer(conn);
+        let session = insert_session(conn, organizer.clone()).unwrap();
+        assert_eq!(authenticate_organizer(conn, Some(&session.token)).unwrap().id, organizer.id);
+
+        let expired = insert_session(conn, organizer).unwrap();
+        diesel
+            ::update(crate::schema::organizer_sessions::table)
+            .filter(crate::schema::organizer_sessions::token_hash.eq(hash_token(&expired.token)))
+            .set(crate::schema::organizer_sessions::expires_at.eq("2000-01-01T00:00:00+00:00"))
+            .execute(conn)
+            .unwrap();
+        assert!(matches!(authenticate_organizer(conn, Some(&expired.token)), Err(AppError::Unauthorized(_))));
+
+        remove_session(conn, Some(&session.token)).unwrap();
+        assert!(authenticate_organizer(conn, Some(&session.token)).is_err());
+        assert!(remove_session(conn, Some(&session.token)).is_err());
+    }
+
+    #[test]
+    fn members_act_in_their_organizations_rooms_by_role() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let organization_id_value = room.organization_id.clone().unwrap();
+        let viewer = test_organizer(conn);
+        let outsider = test_organizer(conn);
+        insert_member(conn, &organization_id_value, &viewer.email, MemberRole::Viewer).unwrap();
+
+        let viewer_session = insert_session(conn, viewer.clone()).unwrap();
+        let caller = authorize(conn, &bearer(&viewer_session.token), &room.id, EVERYONE).unwrap();
+        assert_eq!((caller.role, caller.tenant()), (Role::Audience, Some(organization_id_value.as_str())));
+        assert!(matches!(authorize(conn, &bearer(&viewer_session.token), &room.id, HOST), Err(AppError::Forbidden(_))));
+        assert!(authorize_member(conn, Some(&viewer_session.token), &organization_id_value, ORG_ORGANIZERS).is_err());
+        assert!(matches!(
+            insert_member(conn, &organization_id_value, &viewer.email, MemberRole::Organizer),
+            Err(AppError::Conflict(_))
+        ));
+
+        let outsider_session = insert_session(conn, outsider).unwrap();
+        assert!(matches!(authorize(conn, &bearer(&outsider_session.token), &room.id, EVERYONE), Err(AppError::Forbidden(_))));
+        assert!(authorize_member(conn, Some(&outsider_session.token), &organization_id_value, ORG_MEMBERS).is_err());
+    }
+
+    #[test]
+    fn reads_are_scoped_to_the_callers_tenant() {
+        let conn = &mut connection();
+        let (room, poets) = seeded_round(conn, &policy(AggregationMode::Sum, 1, 0), &["A"]);
+        let other = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        submit_score(conn, &poets[0].participation.id, 1, 8.0).unwrap();
+        let tenant = room.organization_id.as_deref();
+        let stranger = other.organization_id.as_deref();
+        let participation_id_value = Some(poets[0].participation.id.clone());
+
+        assert_eq!(retrieve_rooms(conn, tenant, std::slice::from_ref(&room.id)).unwrap().len(), 1);
+        assert!(retrieve_rooms(conn, stranger, std::slice::from_ref(&room.id)).unwrap().is_empty());
+        assert!(retrieve_rooms(conn, None, std::slice::from_ref(&room.id)).unwrap().is_empty());
+        assert_eq!(retrieve_participants(conn, tenant, &room.id).unwrap().len(), 1);
+        assert!(retrieve_participants(conn, stranger, &room.id).unwrap().is_empty());
+        assert_eq!(retrieve_judges(conn, tenant, &room.id).unwrap().len(), 1);
+        assert!(retrieve_judges(conn, stranger, &room.id).unwrap().is_empty());
+        assert_eq!(retrieve_scores(conn, tenant, &participation_id_value, &None).unwrap().len(), 1);
+        assert!(retrieve_scores(conn, stranger, &participation_id_value, &None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_round_only_takes_participants_of_its_room() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let other = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let stranger = insert_participant(conn, "Stranger", None, &other.id, true).unwrap();
+        assert_eq!(stranger.organization_id, other.organization_id);
+
+        let result = create_next_round(conn, &room.id, vec![stranger.clone()], OrderStrategy::Manual, None);
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+
+        let round = create_next_round(conn, &room.id, Vec::new(), OrderStrategy::Manual, None).unwrap();
+        let result = insert_participation(conn, &round.id, &stranger.id, None, None);
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        assert!(retrieve_round(conn, &round.id).unwrap().participations.is_empty());
+    }
+
+    #[test]
+    fn an_organization_keeps_at_least_one_owner() {
+        let conn = &mut connection();
+        let owner = test_member(conn);
+        let second = test_organizer(conn);
+        let added = insert_member(conn, &owner.organization_id, &second.email, MemberRole::Organizer).unwrap();
+
+        assert!(matches!(remove_member(conn, &owner.id), Err(AppError::Conflict(_))));
+        assert!(matches!(update_member_role(conn, &owner.id, MemberRole::Viewer), Err(AppError::Conflict(_))));
+        update_member_role(conn, &added.member.id, MemberRole::Owner).unwrap();
+        update_member_role(conn, &owner.id, MemberRole::Viewer).unwrap();
+        assert!(remove_member(conn, &owner.id).is_ok());
+        assert_eq!(retrieve_members(conn, &owner.organization_id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn new_rooms_go_to_the_organization_asked_for_or_the_only_one() {
+        let conn = &mut connection();
+        let organizer = test_organizer(conn);
+        assert!(matches!(resolve_room_membership(conn, &organizer, None), Err(AppError::Validation { .. })));
+
+        let first = insert_organization(conn, "First", &organizer.id).unwrap().organization;
+        assert_eq!(resolve_room_membership(conn, &organizer, None).unwrap().organization_id, first.id);
+
+        let second = insert_organization(conn, "Second", &organizer.id).unwrap().organization;
+        assert!(matches!(resolve_room_membership(conn, &organizer, None), Err(AppError::Validation { .. })));
+        assert_eq!(resolve_room_membership(conn, &organizer, Some(&second.id)).unwrap().organization_id, second.id);
+
+        let viewed = test_member(conn);
+        insert_member(conn, &viewed.organization_id, &organizer.email, MemberRole::Viewer).unwrap();
+        assert!(matches!(
+            resolve_room_membership(conn, &organizer, Some(&viewed.organization_id)),
+            Err(AppError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn a_seat_is_claimed_once_until_the_host_releases_it() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let claimed = issue_judge_token(conn, &judge_tokens(), &room.join_code, 1).unwrap();
+        assert!(claimed.judge.claimed_at.is_some());
+        assert!(matches!(
+            issue_judge_token(conn, &judge_tokens(), &room.join_code, 1),
+            Err(AppError::Conflict(_))
+        ));
+
+        release_judge_seat(conn, &claimed.judge.id).unwrap();
+        assert!(matches!(authenticate(conn, &bearer(&claimed.token)), Err(AppError::Unauthorized(_))));
+        let reclaimed = issue_judge_token(conn, &judge_tokens(), &room.join_code, 1).unwrap();
+        assert!(authenticate(conn, &bearer(&reclaimed.token)).is_ok());
+    }
+
+    #[test]
+    fn an_update_with_nothing_to_set_is_a_validation_error() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let poet = insert_participant(conn, "Test poet", None, &room.id, true).unwrap();
+
+        assert!(matches!(
+            update_participant(conn, poet.id.clone(), None, None, None),
+            Err(AppError::Validation { message, .. }) if message == "Nothing to update"
+        ));
+    }
+
+    #[test]
+    fn foreign_key_violations_name_the_field() {
+        let conn = &mut connection();
+        let orphan = Score {
+            id: Uuid::new_v4().to_string(),
+            value: 8000,
+            submitter_id: None,
+            participation_id: "no-such-participation".to_owned(),
+        };
+        let result = conn.transaction(|conn| {
+            return diesel::insert_into(crate::schema::scores::table).values(&orphan).execute(conn);
+        });
+
+        assert!(matches!(
+            result.map_err(AppError::from),
+            Err(AppError::Validation { details: Some(details), .. }) if details["field"] == "participation_id"
+        ));
+    }
+
+    #[test]
+    fn sacrificial_poets_sit_out_the_first_cut_and_enter_as_non_competitive() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let poet = insert_participant(conn, "A", None, &room.id, true).unwrap();
+        let sacrifice = insert_participant(conn, "Sacrifice", None, &room.id, false).unwrap();
+
+        let first_field = select_advancing_participants(conn, &room.id).unwrap();
+        assert_eq!(names(&first_field), vec!["A"]);
+        let round = create_next_round(conn, &room.id, vec![poet, sacrifice.clone()], OrderStrategy::Manual, None)
+            .unwrap();
+        let competitive: Vec<(String, bool)> = retrieve_round(conn, &round.id).unwrap().participations
+            .into_iter()
+            .map(|response| (response.participant.name, response.participation.competitive))
+            .collect();
+        assert_eq!(competitive, vec![("A".to_owned(), true), ("Sacrifice".to_owned(), false)]);
+
+        let competing = update_participant(conn, sacrifice.id.clone(), None, None, Some(true)).unwrap();
+        assert!(competing.competitive);
+    }
+
+    #[test]
+    fn saving_a_time_penalty_again_updates_the_same_policy() {
+        let conn = &mut connection();
+        let (room, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        update_participation(conn, participation.id.clone(), None, Some(211), None).unwrap();
+
+        let strict = TimePenaltySettings { grace_seconds: 0, ..TimePenaltySettings::default() };
+        let first = upsert_time_penalty(conn, &room.id, None, &strict).unwrap();
+        let second = upsert_time_penalty(conn, &room.id, None, &TimePenaltySettings::default()).unwrap();
+        assert_eq!(first.id, second.id);
+
+        let saved: i64 = crate::schema::time_penalty_policies::table
+            .filter(crate::schema::time_penalty_policies::room_id.eq(&room.id))
+            .count()
+            .get_result(conn)
+            .unwrap();
+        assert_eq!(saved, 1);
+        assert_eq!(load_participation(conn, &participation.id).deduction, Some(1.0));
+    }
+
+    #[test]
+    fn a_short_panel_is_aggregated_once_every_seated_judge_has_scored() {
+        let conn = &mut connection();
+        let room = insert_test_room(conn, &policy(AggregationMode::Sum, 3, 0), TieBreakMethod::None, Vec::new());
+        insert_judge(conn, &room.id, None, None).unwrap();
+        insert_judge(conn, &room.id, None, None).unwrap();
+        let poet = insert_participant(conn, "A", None, &room.id, true).unwrap();
+        let round = create_next_round(conn, &room.id, vec![poet], OrderStrategy::Manual, None).unwrap();
+        let participation = &retrieve_round(conn, &round.id).unwrap().participations[0].participation;
+
+        submit_score(conn, &participation.id, 1, 8.0).unwrap();
+        assert_eq!(stored_score(conn, &participation.id), None);
+        submit_score(conn, &participation.id, 2, 9.0).unwrap();
+        assert_eq!(stored_score(conn, &participation.id), Some(17000));
+    }
+
+    #[test]
+    fn a_new_scoring_policy_re_aggregates_scored_performances() {
+        let conn = &mut connection();
+        let (room, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 3, 0));
+        score_all(conn, &participation.id, &[7.0, 8.0, 9.0]);
+        assert_eq!(stored_score(conn, &participation.id), Some(24000));
+
+        let trimmed = policy(AggregationMode::TrimmedSum, 3, 1);
+        update_room(conn, room.id.clone(), None, Some(trimmed), None, None, None).unwrap();
+        assert_eq!(stored_score(conn, &participation.id), Some(8000));
+
+        let mean = policy(AggregationMode::Mean, 3, 0);
+        update_room(conn, room.id.clone(), None, Some(mean), None, None, None).unwrap();
+        assert_eq!(stored_score(conn, &participation.id), Some(8000));
+    }
+
+    #[test]
+    fn judge_count_cannot_outgrow_the_seated_judges_once_scoring_started() {
+        let conn = &mut connection();
+        let (room, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        let larger = policy(AggregationMode::Sum, 3, 0);
+        update_room(conn, room.id.clone(), None, Some(larger.clone()), None, None, None).unwrap();
+        update_room(conn, room.id.clone(), None, Some(policy(AggregationMode::Sum, 2, 0)), None, None, None)
+            .unwrap();
+
+        submit_score(conn, &participation.id, 1, 8.0).unwrap();
+        assert!(matches!(
+            update_room(conn, room.id.clone(), None, Some(larger), None, None, None),
+            Err(AppError::Validation { details: Some(details), .. }) if details["field"] == "judge_count"
+        ));
+    }
+
+    #[test]
+    fn ids_from_other_rooms_look_missing() {
+        let conn = &mut connection();
+        let (room, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let other = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let host = bearer(&insert_credential(conn, &room.id, Role::Host, None, None).unwrap().token);
+        let audience = bearer(&insert_credential(conn, &room.id, Role::Audience, None, None).unwrap().token);
+        let outsider = bearer(&insert_credential(conn, &other.id, Role::Host, None, None).unwrap().token);
+
+        let (found, _) = authorize_round(conn, &host, &participation.round_id, HOST).unwrap();
+        assert_eq!(found.id, room.id);
+        assert!(matches!(
+            authorize_round(conn, &audience, &participation.round_id, HOST),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(matches!(
+            authorize_round(conn, &outsider, &participation.round_id, HOST),
+            Err(AppError::NotFound(_))
+        ));
+        assert!(matches!(
+            authorize_participation(conn, &outsider, &participation.id, EVERYONE),
+            Err(AppError::NotFound(_))
+        ));
+        assert!(matches!(authorize_round(conn, &outsider, "no-such-round", HOST), Err(AppError::NotFound(_))));
+        assert!(matches!(
+            authorize_round(conn, &bearer("made-up"), &participation.round_id, HOST),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            authorize_round(conn, &bearer("made-up"), "no-such-round", HOST),
+            Err(AppError::Unauthorized(_))
+        ));
+    }
+
+    #[test]
+    fn a_room_short_of_judges_can_still_change_its_mode_and_range() {
+        let conn = &mut connection();
+        let room = insert_test_room(conn, &policy(AggregationMode::Sum, 3, 0), TieBreakMethod::None, Vec::new());
+        insert_judge(conn, &room.id, None, None).unwrap();
+        insert_judge(conn, &room.id, None, None).unwrap();
+        let poet = insert_participant(conn, "A", None, &room.id, true).unwrap();
+        let round = create_next_round(conn, &room.id, vec![poet], OrderStrategy::Manual, None).unwrap();
+        let participation = &retrieve_round(conn, &round.id).unwrap().participations[0].participation;
+        score_all(conn, &participation.id, &[8.0, 9.0]);
+
+        let mean = policy(AggregationMode::Mean, 3, 0);
+        update_room(conn, room.id.clone(), None, Some(mean), None, None, None).unwrap();
+        assert_eq!(stored_score(conn, &participation.id), Some(8500));
+
+        let range = ScoreRange { min: 0.0, max: 20.0, precision: 1 };
+        let updated = update_room(conn, room.id.clone(), None, None, None, None, Some(range)).unwrap();
+        assert_eq!(updated.score_max, 20.0);
+        assert_eq!(stored_score(conn, &participation.id), Some(8500));
+    }
+
+    #[test]
+    fn a_removed_judges_scores_go_with_them() {
+        let conn = &mut connection();
+        let (room, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 2, 0));
+        score_all(conn, &participation.id, &[7.0, 9.0]);
+        let judges = retrieve_judges(conn, room.organization_id.as_deref(), &room.id).unwrap();
+
+        remove_judge(conn, judges[1].id.clone()).unwrap();
+        let tenant = room.organization_id.as_deref();
+        assert_eq!(retrieve_scores(conn, tenant, &Some(participation.id.clone()), &None).unwrap().len(), 1);
+        assert_eq!(stored_score(conn, &participation.id), Some(7000));
+
+        insert_judge(conn, &room.id, None, None).unwrap();
+        submit_score(conn, &participation.id, 2, 8.0).unwrap();
+        assert_eq!(stored_score(conn, &participation.id), Some(15000));
+    }
+
+    #[test]
+    fn seats_above_a_lowered_judge_count_stop_counting() {
+        let conn = &mut connection();
+        let (room, participation) = seeded_room(conn, &policy(AggregationMode::Sum, 3, 0));
+        score_all(conn, &participation.id, &[7.0, 8.0, 9.0]);
+
+        update_room(conn, room.id.clone(), None, Some(policy(AggregationMode::Sum, 2, 0)), None, None, None)
+            .unwrap();
+        assert_eq!(stored_score(conn, &participation.id), Some(15000));
+        assert!(matches!(submit_score(conn, &participation.id, 3, 6.0), Err(AppError::Forbidden(_))));
+    }
+
+    #[test]
+    fn rooms_without_a_host_get_one_that_can_claim_them() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        insert_participant(conn, "A", None, &room.id, true).unwrap();
+        diesel::update(crate::schema::rooms::table.find(&room.id))
+            .set((
+                crate::schema::rooms::organizer_id.eq(None::<String>),
+                crate::schema::rooms::organization_id.eq(None::<String>),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        let issued = issue_missing_host_credentials(conn).unwrap();
+        let host_token = issued
+            .into_iter()
+            .find(|issued| issued.credential.room_id == room.id)
+            .map(|issued| issued.token)
+            .unwrap();
+        assert!(issue_missing_host_credentials(conn).unwrap().is_empty());
+
+        let member = test_member(conn);
+        let organizer: Organizer = crate::schema::organizers::table.find(&member.organizer_id).first(conn).unwrap();
+        assert!(matches!(claim_room(conn, &organizer, &room.id, "made-up", None), Err(AppError::Forbidden(_))));
+        let claimed = claim_room(conn, &organizer, &room.id, &host_token, None).unwrap();
+        assert_eq!(claimed.organization_id.as_ref(), Some(&member.organization_id));
+        let tenant = Some(member.organization_id.as_str());
+        assert_eq!(retrieve_participants(conn, tenant, &room.id).unwrap().len(), 1);
+        assert_eq!(retrieve_judges(conn, tenant, &room.id).unwrap().len(), 1);
+        assert!(matches!(
+            claim_room(conn, &organizer, &room.id, &host_token, None),
+            Err(AppError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn leaderboard_totals_add_up_in_thousandths() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        let poet = insert_participant(conn, "A", None, &room.id, true).unwrap();
+        for value_value in [8.1, 8.2, 8.3, 8.4, 8.5] {
+            let round = create_next_round(conn, &room.id, vec![poet.clone()], OrderStrategy::Manual, None).unwrap();
+            let participation = &retrieve_round(conn, &round.id).unwrap().participations[0].participation;
+            submit_score(conn, &participation.id, 1, value_value).unwrap();
+        }
+
+        let leaderboard = retrieve_room_leaderboard(conn, &room.id, CoinFlips::Keep).unwrap().leaderboard;
+        assert_eq!(leaderboard[0].total, 41500);
+        assert_eq!(serde_json::to_value(&leaderboard[0]).unwrap()["total"], serde_json::json!(41.5));
+    }
+
+    #[test]
+    fn deleting_an_organizer_leaves_their_rooms_unowned() {
+        let conn = &mut connection();
+        let room = test_room(conn, &policy(AggregationMode::Sum, 1, 0));
+        diesel::delete(crate::schema::organizers::table.find(room.organizer_id.as_ref().unwrap()))
+            .execute(conn)
+            .unwrap();
+
+        let kept: Room = crate::schema::rooms::table.find(&room.id).first(conn).unwrap();
+        assert_eq!(kept.organizer_id, None);
+        assert_eq!(retrieve_judges(conn, room.organization_id.as_deref(), &room.id).unwrap().len(), 1);
+    }
+}
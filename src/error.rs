@@ -0,0 +1,150 @@
+use axum::{ http::StatusCode, response::{ IntoResponse, Response }, Json };
+use diesel::result::{ DatabaseErrorKind, Error as DieselError };
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    NotFound(String),
+    Conflict(String),
+    Validation {
+        message: String,
+        details: Option<Value>,
+    },
+    Unauthorized(String),
+    Forbidden(String),
+    Internal(String),
+}
+
+/// JSON body of every error response. `details` is merged into the top level so
+/// validation errors can say which field failed and why.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorBody {
+    pub error: &'static str,
+    pub message: String,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl AppError {
+    pub fn validation(message: impl Into<String>) -> AppError {
+        return AppError::Validation { message: message.into(), details: None };
+    }
+
+    pub fn status(&self) -> StatusCode {
+        return match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Validation { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        return match self {
+            AppError::NotFound(message) => ErrorBody {
+                error: "not_found",
+                message: message.clone(),
+                details: None,
+            },
+            AppError::Conflict(message) => ErrorBody {
+                error: "conflict",
+                message: message.clone(),
+                details: None,
+            },
+            AppError::Validation { message, details } => ErrorBody {
+                error: "validation",
+                message: message.clone(),
+                details: details.clone(),
+            },
+            AppError::Unauthorized(message) => ErrorBody {
+                error: "unauthorized",
+                message: message.clone(),
+                details: None,
+            },
+            AppError::Forbidden(message) => ErrorBody {
+                error: "forbidden",
+                message: message.clone(),
+                details: None,
+            },
+            // The cause is logged, not sent to the client.
+            AppError::Internal(_) => ErrorBody {
+                error: "internal",
+                message: "Internal server error".to_owned(),
+                details: None,
+            },
+        };
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            AppError::Internal(message) => write!(f, "internal error: {}", message),
+            _ => write!(f, "{}", self.body().message),
+        };
+    }
+}
+
+impl std::error::Error for AppError {}
+
+const EMPTY_CHANGESET_MESSAGE: &str = "There are no changes to save. This query cannot be built";
+
+impl From<DieselError> for AppError {
+    fn from(error: DieselError) -> AppError {
+        return match error {
+            DieselError::NotFound => AppError::NotFound("Record not found".to_owned()),
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) =>
+                AppError::Conflict(info.message().to_owned()),
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) =>
+                foreign_key_error(info.details().unwrap_or_default()),
+            // Diesel has no dedicated error for an update with nothing to set, only this message.
+            DieselError::QueryBuilderError(error) if error.to_string() == EMPTY_CHANGESET_MESSAGE =>
+                AppError::validation("Nothing to update"),
+            error => AppError::Internal(error.to_string()),
+        };
+    }
+}
+
+/// Postgres explains a foreign key violation as `Key (participant_id)=(...) is
+/// not present in table "participants".`, or `... is still referenced from
+/// table "..."` when deleting. The first names the field the client got wrong.
+fn foreign_key_error(details: &str) -> AppError {
+    if details.contains("is still referenced") {
+        return AppError::Conflict("Record is still in use".to_owned());
+    }
+    let field = details
+        .strip_prefix("Key (")
+        .and_then(|rest| rest.split_once(')'))
+        .map(|(field, _)| field);
+    return match field {
+        Some(field) => AppError::Validation {
+            message: format!("{} does not refer to an existing record", field),
+            details: Some(serde_json::json!({ "field": field })),
+        },
+        None => AppError::validation("Referenced record does not exist"),
+    };
+}
+
+impl From<diesel::ConnectionError> for AppError {
+    fn from(error: diesel::ConnectionError) -> AppError {
+        return AppError::Internal(error.to_string());
+    }
+}
+
+impl From<diesel::r2d2::PoolError> for AppError {
+    fn from(error: diesel::r2d2::PoolError) -> AppError {
+        return AppError::Internal(error.to_string());
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        if let AppError::Internal(message) = &self {
+            tracing::error!("internal error: {}", message);
+        }
+        return (self.status(), Json(self.body())).into_response();
+    }
+}
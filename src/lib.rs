@@ -1,3 +1,12 @@
+#![allow(clippy::needless_return)]
+
 pub mod models;
 pub mod schema;
-pub mod db;
\ No newline at end of file
+pub mod db;
+pub mod error;
+pub mod access;
+pub mod advancement;
+pub mod channels;
+pub mod scoring;
+pub mod standings;
+pub mod timer;
\ No newline at end of file
@@ -1,46 +1,104 @@
-use std::{ env, sync::Arc };
+#![allow(clippy::needless_return)]
+
+use std::{ collections::VecDeque, convert::Infallible, env, sync::Arc };
 
 use axum::{
-    routing::{ get, patch, post },
-    http::StatusCode,
+    routing::{ delete, get, patch, post },
+    http::{ HeaderMap, StatusCode },
     Json,
     Router,
-    extract::{ Query, Path, ws::{ WebSocketUpgrade, WebSocket, Message }, State },
-    response::{ IntoResponse, Response },
+    extract::{ Query, Path, ws::{ close_code, CloseFrame, WebSocketUpgrade, WebSocket, Message }, State },
+    response::{ IntoResponse, Response, sse::{ Event, KeepAlive, Sse } },
 };
 use serde_json::json;
 use tower_http::{ trace::TraceLayer, cors::CorsLayer, services::ServeDir, services::ServeFile };
 use dotenv::dotenv;
-use slam_app_rust_server::{ db::*, models::* };
-use tokio::{ sync::broadcast };
-use futures::{ sink::SinkExt, stream::StreamExt };
+use slam_app_rust_server::{
+    access::*,
+    advancement::*,
+    channels::*,
+    db::*,
+    error::AppError,
+    models::*,
+    scoring::*,
+    standings::*,
+    timer::*,
+};
+use diesel::{ pg::PgConnection, Connection };
+use futures::{ sink::SinkExt, stream::{ self, SplitSink, StreamExt } };
+use tokio::sync::broadcast;
 
-#[derive(Clone)]
 struct AppState {
-    tx: broadcast::Sender<String>,
+    channels: Arc<RoomChannels>,
+    timers: Timers,
+    judge_tokens: Arc<JudgeTokens>,
+    pool: DbPool,
+}
+
+impl JudgeTokenState for AppState {
+    fn judge_tokens(&self) -> Arc<JudgeTokens> {
+        return self.judge_tokens.clone();
+    }
 }
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
-    run_migration();
+    tracing_subscriber::fmt::init();
+    let judge_tokens = Arc::new(JudgeTokens::from_env().unwrap_or_else(|error| panic!("{}", error)));
+    let pool = establish_pool();
+    run_migration(&pool);
+    issue_host_credentials(&pool);
 
     let serve_dir = ServeDir::new("./build").not_found_service(
         ServeFile::new("./build/index.html")
     );
 
-    let (tx, _rx) = broadcast::channel(100);
-    let state = Arc::new(AppState { tx });
+    let channels = Arc::new(RoomChannels::new(CHANNEL_CAPACITY, EVENT_HISTORY_SIZE, EVENT_HISTORY_IDLE));
+    let state = Arc::new(AppState { channels, timers: Timers::default(), judge_tokens, pool });
 
     let app = Router::new()
         .route("/data/room", get(get_rooms).post(post_room))
         .route("/data/room/:id", get(get_room).patch(patch_room).delete(delete_room))
         .route("/data/room/:id/advance", post(advance_room))
-        .route("/data/room/:id/current", get(current_room))
+        .route("/data/room/:id/claim", post(claim_unowned_room))
+        .route("/data/room/:id/credential", get(get_credentials).post(post_credential))
+        .route("/data/room/:id/join-code", get(get_join_code).post(rotate_join_code))
+        .route("/data/room/:id/current", get(current_room).put(put_current).delete(delete_current))
+        .route("/data/room/:id/current/next", post(next_performer))
+        .route("/data/room/:id/current/previous", post(previous_performer))
+        .route("/data/room/:id/events", get(room_events))
+        .route("/data/room/:id/leaderboard", get(get_leaderboard))
+        .route("/data/room/:id/leaderboard/coin-flip", post(post_leaderboard_coin_flip))
+        .route("/data/room/:id/time-penalty", get(get_room_time_penalty).put(put_room_time_penalty))
         .route("/data/participant", get(get_participants).post(post_participant))
         .route("/data/participant/:id", patch(patch_participant).delete(delete_participant))
+        .route("/data/judge", get(get_judges).post(post_judge))
+        .route("/data/judge/:id", patch(patch_judge).delete(delete_judge))
+        .route("/data/judge/:id/release", post(release_judge))
+        .route("/data/credential/:id", delete(delete_credential))
+        .route("/data/organizer", post(post_organizer))
+        .route("/data/organization", get(get_organizations).post(post_organization))
+        .route("/data/organization/:id", get(get_organization).patch(patch_organization))
+        .route("/data/organization/:id/member", get(get_members).post(post_member))
+        .route("/data/member/:id", patch(patch_member).delete(delete_member))
+        .route("/data/session", get(get_session).post(post_session).delete(delete_session))
+        .route("/data/join", post(post_join))
+        .route("/data/join/:code", get(get_join))
         .route("/data/round/:id", get(get_round))
+        .route("/data/round/:id/standings", get(get_standings))
+        .route("/data/round/:id/standings/coin-flip", post(post_standings_coin_flip))
+        .route("/data/round/:id/participation", post(post_participation))
+        .route(
+            "/data/round/:id/time-penalty",
+            get(get_round_time_penalty)
+                .put(put_round_time_penalty)
+                .delete(delete_round_time_penalty)
+        )
         .route("/data/participation/:id", patch(patch_participation))
+        .route("/data/participation/:id/timer/start", post(start_participation_timer))
+        .route("/data/participation/:id/timer/stop", post(stop_participation_timer))
+        .route("/data/participation/:id/timer/reset", post(reset_participation_timer))
         .route("/data/score", get(get_scores).post(post_score))
         .route("/data/ws", get(websocket_handler))
         .with_state(state)
@@ -56,164 +114,1306 @@ async fn main() {
         .unwrap();
 }
 
-async fn websocket_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+/// Rooms from before credentials existed have no host. Each gets a host
+/// credential at startup, and its token is logged this once so the operator can
+/// hand it to whoever runs the room, who can then claim it for an organization.
+fn issue_host_credentials(pool: &DbPool) {
+    let mut conn = pool.get().expect("Error connecting to database");
+    let issued = issue_missing_host_credentials(&mut conn).expect("Error issuing host credentials");
+    for host_credential in issued {
+        tracing::warn!(
+            "room {} had no host credential; issued host token {}",
+            host_credential.credential.room_id,
+            host_credential.token
+        );
+    }
+}
+
+async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    params: Query<WebsocketFilter>
+) -> Result<Response, AppError> {
+    let room_id = params.room_id.clone().ok_or_else(|| AppError::validation("room_id is required"))?;
+    let token = token.or_query(params.token.clone());
+    let room_id_value = room_id.clone();
+    let token_value = token.clone();
+    with_connection(&state.pool, move |conn|
+        authorize(conn, &token_value, &room_id_value, EVERYONE)
+    ).await?;
+    let epoch = params.epoch.clone();
+    let last_seq = params.last_seq;
+    return Ok(
+        ws.on_upgrade(move |socket| handle_socket(socket, state, token, room_id, epoch, last_seq))
+    );
 }
 
-async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
+async fn handle_socket(
+    socket: WebSocket,
+    state: Arc<AppState>,
+    token: AccessToken,
+    room_id: String,
+    epoch: Option<String>,
+    last_seq: Option<u64>
+) {
     let (mut sender, mut receiver) = socket.split();
 
-    let mut rx = state.tx.subscribe();
+    let (mut subscription, mut delivered) = state.channels.subscribe(&room_id);
+    let mut pending = Vec::new();
+    if let Some(last_seq) = last_seq {
+        match resync(&state, &room_id, epoch.as_deref(), last_seq).await {
+            Ok((messages, seq)) => {
+                pending = messages;
+                delivered = delivered.max(seq);
+            }
+            Err(error) => {
+                close_with_error(&mut sender, error).await;
+                return;
+            }
+        }
+    }
 
-    let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
+    loop {
+        for message in pending.drain(..) {
             // In any websocket error, break loop.
-            if sender.send(Message::Text(msg)).await.is_err() {
-                break;
+            if sender.send(Message::Text(message.text)).await.is_err() {
+                return;
             }
         }
-    });
 
-    let tx = state.tx.clone();
+        tokio::select! {
+            message = subscription.recv() => {
+                match message {
+                    Ok(message) => {
+                        if message.transient {
+                            pending.push(message);
+                        } else if message.seq > delivered {
+                            // Otherwise already sent as part of a resync.
+                            delivered = message.seq;
+                            pending.push(message);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        let epoch = state.channels.epoch();
+                        match resync(&state, subscription.room_id(), Some(epoch), delivered).await {
+                            Ok((messages, seq)) => {
+                                pending = messages;
+                                delivered = delivered.max(seq);
+                            }
+                            Err(error) => {
+                                close_with_error(&mut sender, error).await;
+                                return;
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        break;
+                    }
+                }
+            }
+            inbound = receiver.next() => {
+                let text = match inbound {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                        break;
+                    }
+                    Some(Ok(_)) => {
+                        continue;
+                    }
+                };
+                let reply = match handle_command(&state, &token, subscription.room_id(), &text).await {
+                    Ok(CommandOutcome::Reply(reply)) => reply,
+                    Ok(CommandOutcome::Subscribe(new_room_id, epoch, last_seq)) => {
+                        drop(subscription);
+                        (subscription, delivered) = state.channels.subscribe(&new_room_id);
+                        let joined = delivered;
+                        if let Some(last_seq) = last_seq {
+                            match resync(&state, &new_room_id, epoch.as_deref(), last_seq).await {
+                                Ok((messages, seq)) => {
+                                    pending = messages;
+                                    delivered = delivered.max(seq);
+                                }
+                                Err(error) => {
+                                    close_with_error(&mut sender, error).await;
+                                    return;
+                                }
+                            }
+                        }
+                        CommandReply::Subscribed {
+                            room_id: new_room_id,
+                            epoch: state.channels.epoch().to_owned(),
+                            seq: joined,
+                        }
+                    }
+                    Err(error) => {
+                        let body = error.body();
+                        CommandReply::Rejected { error: body.error.to_owned(), message: body.message }
+                    }
+                };
+                // Replies aren't room events, so they carry no sequence number.
+                let reply = SequencedMessage { seq: 0, text: json!(reply).to_string(), transient: true };
+                pending.insert(0, reply);
+            }
+        }
+    }
+}
+
+/// Tells a client it can't be brought back in step with its room, then closes
+/// the socket so it reconnects instead of waiting on a stream with gaps.
+async fn close_with_error(sender: &mut SplitSink<WebSocket, Message>, error: AppError) {
+    let body = error.body();
+    let reply = CommandReply::Error { error: body.error.to_owned(), message: body.message };
+    let _ = sender.send(Message::Text(json!(reply).to_string())).await;
+    let frame = CloseFrame { code: close_code::ERROR, reason: body.error.into() };
+    let _ = sender.send(Message::Close(Some(frame))).await;
+}
+
+/// The same room events as the websocket, as a Server-Sent Events stream. Each
+/// event's id is `<epoch>:<seq>`, so a reconnecting `EventSource` resumes
+/// through `Last-Event-ID`; other clients can pass `epoch` and `last_seq` instead.
+async fn room_events(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    params: Query<EventStreamFilter>,
+    headers: HeaderMap
+) -> Result<Response, AppError> {
+    let last_event_id = match headers.get("last-event-id") {
+        Some(value) =>
+            Some(
+                value
+                    .to_str()
+                    .ok()
+                    .and_then(parse_event_id)
+                    .ok_or_else(|| AppError::validation("Last-Event-ID must be an event id"))?
+            ),
+        None => None,
+    };
+    let token = token.or_query(params.token.clone());
+    let caller = with_connection(&state.pool, move |conn|
+        authorize(conn, &token, &id, EVERYONE)
+    ).await?;
+    let room_id = caller.room_id;
+
+    let (subscription, mut delivered) = state.channels.subscribe(&room_id);
+    let mut pending = VecDeque::new();
+    let resume = last_event_id.or_else(|| params.last_seq.map(|last_seq| (params.epoch.clone(), last_seq)));
+    if let Some((epoch, last_seq)) = resume {
+        let (messages, seq) = resync(&state, &room_id, epoch.as_deref(), last_seq).await?;
+        pending.extend(messages);
+        delivered = delivered.max(seq);
+    }
 
-    let mut recv_task = tokio::spawn(async move {
-        while let Some(Ok(Message::Text(text))) = receiver.next().await {
-            let _ = tx.send(text);
+    let feed = EventFeed { state, subscription, delivered, pending };
+    let events = stream::unfold(feed, |mut feed| async move {
+        let message = feed.next().await?;
+        let mut event = Event::default().data(message.text);
+        // Transient messages leave the id alone so the browser resumes from the last event.
+        if !message.transient {
+            event = event.id(format!("{}:{}", feed.state.channels.epoch(), message.seq));
         }
+        return Some((Ok::<Event, Infallible>(event), feed));
     });
+    return Ok(Sse::new(events).keep_alive(KeepAlive::default()).into_response());
+}
+
+/// Splits an SSE event id into its epoch and sequence number. A bare number
+/// is accepted too, but has no epoch to vouch for it.
+fn parse_event_id(value: &str) -> Option<(Option<String>, u64)> {
+    return match value.split_once(':') {
+        Some((epoch, seq)) => Some((Some(epoch.to_owned()), seq.parse().ok()?)),
+        None => Some((None, value.parse().ok()?)),
+    };
+}
+
+struct EventFeed {
+    state: Arc<AppState>,
+    subscription: Subscription,
+    delivered: u64,
+    pending: VecDeque<SequencedMessage>,
+}
 
-    tokio::select! {
-        _ = (&mut send_task) => recv_task.abort(),
-        _ = (&mut recv_task) => send_task.abort(),
+impl EventFeed {
+    /// The next message to send, or `None` once the room is gone or the feed
+    /// can't be brought back in step.
+    async fn next(&mut self) -> Option<SequencedMessage> {
+        loop {
+            if let Some(message) = self.pending.pop_front() {
+                return Some(message);
+            }
+            match self.subscription.recv().await {
+                Ok(message) => {
+                    if message.transient {
+                        return Some(message);
+                    }
+                    // Otherwise already sent as part of a resync.
+                    if message.seq > self.delivered {
+                        self.delivered = message.seq;
+                        return Some(message);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let room_id = self.subscription.room_id().to_owned();
+                    let epoch = self.state.channels.epoch().to_owned();
+                    let (messages, seq) = resync(&self.state, &room_id, Some(&epoch), self.delivered).await.ok()?;
+                    self.pending.extend(messages);
+                    self.delivered = self.delivered.max(seq);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    return None;
+                }
+            }
+        }
     }
 }
 
-async fn get_rooms() -> (StatusCode, Json<Vec<Room>>) {
-    let rooms_result = retrieve_rooms(&mut establish_connection());
-    return (StatusCode::OK, Json(rooms_result));
+/// Messages that bring a client from `last_seq` up to date, and the sequence
+/// number they bring it to. Falls back to a full snapshot when the room's
+/// history no longer covers the gap, or `last_seq` is from another epoch.
+async fn resync(
+    state: &Arc<AppState>,
+    room_id: &str,
+    epoch: Option<&str>,
+    last_seq: u64
+) -> Result<(Vec<SequencedMessage>, u64), AppError> {
+    match state.channels.since(room_id, epoch, last_seq) {
+        Resync::Missed(messages) => {
+            let seq = messages.last().map_or(last_seq, |message| message.seq);
+            return Ok((messages, seq));
+        }
+        Resync::Snapshot => {
+            let seq = state.channels.last_seq(room_id);
+            let room_id_value = room_id.to_owned();
+            let (room, current_round) = with_connection(&state.pool, move |conn| {
+                let room = retrieve_room(conn, &room_id_value)?;
+                let current_round = match &room.room.round_id_current {
+                    Some(round_id) => Some(retrieve_round(conn, round_id)?),
+                    None => None,
+                };
+                Ok((room, current_round))
+            }).await?;
+            let snapshot = EventMessage::new(
+                room_id,
+                state.channels.epoch(),
+                seq,
+                RoomEvent::Snapshot { room, current_round }
+            );
+            let message = SequencedMessage { seq, text: json!(snapshot).to_string(), transient: false };
+            return Ok((vec![message], seq));
+        }
+    }
 }
-async fn get_room(Path(id): Path<String>) -> (StatusCode, Json<RoomResponse>) {
-    let rooms_result = retrieve_room(&mut establish_connection(), id.as_str());
-    return (StatusCode::OK, Json(rooms_result));
+
+enum CommandOutcome {
+    Reply(CommandReply),
+    Subscribe(String, Option<String>, Option<u64>),
 }
-async fn post_room(Json(payload): Json<RoomRequest>) -> Response {
-    if let Some(name) = payload.name {
-        let room_result = insert_room(&mut establish_connection(), &name);
-        return (StatusCode::CREATED, Json(room_result)).into_response();
-    } else {
-        return (StatusCode::BAD_REQUEST, "send better params pls").into_response();
+
+/// Parses and applies one inbound websocket message. Commands only ever act on
+/// the room the connection is subscribed to, and are checked against the
+/// token the connection was opened with.
+async fn handle_command(
+    state: &Arc<AppState>,
+    token: &AccessToken,
+    room_id: &str,
+    text: &str
+) -> Result<CommandOutcome, AppError> {
+    let command: ClientCommand = serde_json
+        ::from_str(text)
+        .map_err(|error| AppError::validation(format!("unrecognised command: {}", error)))?;
+
+    match command {
+        ClientCommand::Ping => {
+            return Ok(CommandOutcome::Reply(CommandReply::Pong));
+        }
+        ClientCommand::Subscribe { room_id: new_room_id, epoch, last_seq } => {
+            let token = token.clone();
+            let caller = with_connection(&state.pool, move |conn|
+                authorize(conn, &token, &new_room_id, EVERYONE)
+            ).await?;
+            return Ok(CommandOutcome::Subscribe(caller.room_id, epoch, last_seq));
+        }
+        ClientCommand::Announce { message } => {
+            let message = validate_announcement(&message)?;
+            let token = token.clone();
+            let room_id_value = room_id.to_owned();
+            with_connection(&state.pool, move |conn|
+                authorize(conn, &token, &room_id_value, HOST)
+            ).await?;
+            publish(state, room_id, RoomEvent::Announcement { message });
+            return Ok(CommandOutcome::Reply(CommandReply::Accepted));
+        }
     }
 }
 
-async fn patch_room(Path(id): Path<String>, Json(payload): Json<RoomRequest>) -> Response {
-    update_room(&mut establish_connection(), id, payload.name);
-    return (StatusCode::OK, "Updated").into_response();
+fn publish(state: &AppState, room_id: &str, event: RoomEvent) {
+    state.channels.send(room_id, event);
+}
+
+async fn get_rooms(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    params: Query<RoomFilter>
+) -> Result<Response, AppError> {
+    // Organizers see the rooms of their organizations; a room credential only its own room.
+    let rooms_result = with_connection(&state.pool, move |conn| {
+        match token.value() {
+            Some(session) if is_session_token(session) => {
+                let organizer = authenticate_organizer(conn, Some(session))?;
+                retrieve_member_rooms(conn, &organizer.id, params.organization_id.as_deref())
+            }
+            _ => {
+                let caller = authenticate(conn, &token)?;
+                retrieve_rooms(conn, caller.organization_id.as_deref(), &[caller.room_id])
+            }
+        }
+    }).await?;
+    return Ok((StatusCode::OK, Json(rooms_result)).into_response());
+}
+async fn get_room(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let rooms_result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, EVERYONE)?;
+        retrieve_room(conn, &id)
+    }).await?;
+    return Ok((StatusCode::OK, Json(rooms_result)).into_response());
+}
+async fn post_room(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Json(payload): Json<RoomRequest>
+) -> Result<Response, AppError> {
+    let name = payload.name.ok_or_else(|| AppError::validation("name is required"))?;
+    let policy = validate_scoring_policy(
+        payload.judge_count.unwrap_or(DEFAULT_JUDGE_COUNT),
+        payload.drop_count.unwrap_or(DEFAULT_DROP_COUNT),
+        payload.aggregation_mode.as_deref().unwrap_or(DEFAULT_AGGREGATION_MODE)
+    )?;
+    let tie_break_method = validate_tie_break_method(
+        payload.tie_break_method.as_deref().unwrap_or(DEFAULT_TIE_BREAK_METHOD)
+    )?;
+    let cut_sizes = payload.cut_sizes.unwrap_or_default();
+    validate_cut_sizes(&cut_sizes)?;
+    let range = validate_score_range(
+        payload.score_min.unwrap_or(DEFAULT_SCORE_MIN),
+        payload.score_max.unwrap_or(DEFAULT_SCORE_MAX),
+        payload.score_precision.unwrap_or(DEFAULT_SCORE_PRECISION),
+        &policy
+    )?;
+    // The room belongs to one of the signed-in organizer's organizations. The
+    // host credential that comes back is for handing the room to someone
+    // without an account.
+    let room_result = with_connection(&state.pool, move |conn| {
+        let organizer = authenticate_organizer(conn, token.value())?;
+        let creator = resolve_room_membership(conn, &organizer, payload.organization_id.as_deref())?;
+        conn.transaction(|conn| {
+            let room = insert_room(conn, &name, &policy, tie_break_method, cut_sizes, &range, &creator)?;
+            let host_credential = insert_credential(conn, &room.id, Role::Host, None, Some("host".to_owned()))?;
+            Ok(CreatedRoomResponse { join_code: room.join_code.clone(), room, host_credential })
+        })
+    }).await?;
+    return Ok((StatusCode::CREATED, Json(room_result)).into_response());
+}
+
+/// Moves a room that no organization owns into one of the signed-in
+/// organizer's, given one of the room's host tokens.
+async fn claim_unowned_room(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<RoomClaimRequest>
+) -> Result<Response, AppError> {
+    let host_token = payload.host_token.ok_or_else(|| AppError::validation("host_token is required"))?;
+    let room = with_connection(&state.pool, move |conn| {
+        let organizer = authenticate_organizer(conn, token.value())?;
+        claim_room(conn, &organizer, &id, &host_token, payload.organization_id.as_deref())
+    }).await?;
+    let room_id = room.id.clone();
+    publish(&state, &room_id, RoomEvent::RoomUpdated { room: room.clone() });
+    return Ok((StatusCode::OK, Json(room)).into_response());
+}
+
+async fn patch_room(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<RoomRequest>
+) -> Result<Response, AppError> {
+    let tie_break_method = payload.tie_break_method
+        .as_deref()
+        .map(validate_tie_break_method)
+        .transpose()?;
+    if let Some(cut_sizes) = &payload.cut_sizes {
+        validate_cut_sizes(cut_sizes)?;
+    }
+    let renamed = payload.name.is_some();
+    // Only a new policy re-aggregates the room's scores, so the range alone
+    // doesn't build one.
+    let policy_changed =
+        payload.judge_count.is_some() || payload.drop_count.is_some() || payload.aggregation_mode.is_some();
+    let range_changed =
+        payload.score_min.is_some() || payload.score_max.is_some() || payload.score_precision.is_some();
+    let reconfigured =
+        policy_changed || range_changed || tie_break_method.is_some() || payload.cut_sizes.is_some();
+    let room = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        let mut policy = None;
+        let mut range = None;
+        if policy_changed || range_changed {
+            // The range is checked against the policy it will be used with, even
+            // when only one of them changes.
+            let room = retrieve_room(conn, id.as_str())?.room;
+            let new_policy = validate_scoring_policy(
+                payload.judge_count.unwrap_or(room.judge_count),
+                payload.drop_count.unwrap_or(room.drop_count),
+                payload.aggregation_mode.as_deref().unwrap_or(&room.aggregation_mode)
+            )?;
+            let new_range = validate_score_range(
+                payload.score_min.unwrap_or(room.score_min),
+                payload.score_max.unwrap_or(room.score_max),
+                payload.score_precision.unwrap_or(room.score_precision),
+                &new_policy
+            )?;
+            policy = policy_changed.then_some(new_policy);
+            range = range_changed.then_some(new_range);
+        }
+        update_room(conn, id, payload.name, policy, tie_break_method, payload.cut_sizes, range)
+    }).await?;
+
+    if renamed {
+        publish(&state, &room.id, RoomEvent::RoomRenamed { name: room.name.clone() });
+    }
+    if reconfigured {
+        let room_id = room.id.clone();
+        publish(&state, &room_id, RoomEvent::RoomUpdated { room });
+    }
+
+    return Ok((StatusCode::OK, "Updated").into_response());
 }
 
-async fn delete_room(Path(id): Path<String>) -> Response {
-    remove_room(&mut establish_connection(), id);
-    return (StatusCode::OK, "Deleted").into_response();
+async fn delete_room(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let room = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        remove_room(conn, id)
+    }).await?;
+    state.timers.stop_room(&room.id);
+    publish(&state, &room.id, RoomEvent::RoomDeleted { room_id: room.id.clone() });
+    state.channels.remove(&room.id);
+    return Ok((StatusCode::OK, "Deleted").into_response());
 }
 
 async fn patch_participant(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
     Path(id): Path<String>,
     Json(payload): Json<ParticipantRequest>
-) -> Response {
-    update_participant(&mut establish_connection(), id, payload.name, payload.pronouns);
-    return (StatusCode::OK, "Updated").into_response();
+) -> Result<Response, AppError> {
+    let participant = with_connection(&state.pool, move |conn| {
+        authorize_participant(conn, &token, &id, HOST)?;
+        update_participant(conn, id, payload.name, payload.pronouns, payload.competitive)
+    }).await?;
+    let room_id = participant.room_id.clone();
+    publish(&state, &room_id, RoomEvent::ParticipantUpdated { participant });
+    return Ok((StatusCode::OK, "Updated").into_response());
 }
 
-async fn delete_participant(Path(id): Path<String>) -> Response {
-    remove_participant(&mut establish_connection(), id);
-    return (StatusCode::OK, "Deleted").into_response();
+async fn delete_participant(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let participant = with_connection(&state.pool, move |conn| {
+        authorize_participant(conn, &token, &id, HOST)?;
+        remove_participant(conn, id)
+    }).await?;
+    state.timers.stop_participant(&participant.id);
+    publish(&state, &participant.room_id, RoomEvent::ParticipantRemoved {
+        participant_id: participant.id.clone(),
+    });
+    return Ok((StatusCode::OK, "Deleted").into_response());
 }
 
-async fn post_participant(Json(payload): Json<ParticipantRequest>) -> Response {
+async fn post_participant(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Json(payload): Json<ParticipantRequest>
+) -> Result<Response, AppError> {
     if let (Some(name), Some(room_id)) = (payload.name, payload.room_id) {
-        let participant_result = insert_participant(
-            &mut establish_connection(),
-            &name,
-            payload.pronouns,
-            &room_id
-        );
-        return (StatusCode::CREATED, Json(participant_result)).into_response();
+        let participant_result = with_connection(&state.pool, move |conn| {
+            authorize(conn, &token, &room_id, HOST)?;
+            insert_participant(conn, &name, payload.pronouns, &room_id, payload.competitive.unwrap_or(true))
+        }).await?;
+        publish(&state, &participant_result.room_id, RoomEvent::ParticipantAdded {
+            participant: participant_result.clone(),
+        });
+        return Ok((StatusCode::CREATED, Json(participant_result)).into_response());
     } else {
-        return (StatusCode::BAD_REQUEST, "send better params pls").into_response();
+        return Err(AppError::validation("name and room_id are required"));
     }
 }
 async fn post_score(
     State(state): State<Arc<AppState>>,
+    token: AccessToken,
     Json(payload): Json<ScoreRequest>
-) -> Response {
-    let score_result = insert_score(
-        &mut establish_connection(),
-        &payload.value,
-        &payload.participation_id,
-        &payload.submitter_id
-    );
+) -> Result<Response, AppError> {
+    let (score_result, room, judge, participation) = with_connection(&state.pool, move |conn| {
+        let (room, caller) = authorize_participation(conn, &token, &payload.participation_id, SCORERS)?;
+        // Judges always score as the seat their token is for; only a host names
+        // the submitter, when entering a score on a judge's behalf.
+        let submitter_id = match (caller.judge_id, payload.submitter_id) {
+            (Some(judge_id), Some(submitter_id)) if submitter_id != judge_id => {
+                return Err(AppError::Forbidden("judges may only submit their own scores".to_owned()));
+            }
+            (Some(judge_id), _) => judge_id,
+            (None, Some(submitter_id)) => submitter_id,
+            (None, None) => {
+                return Err(AppError::validation("submitter_id is required"));
+            }
+        };
+        let score = insert_score(conn, &payload.value, &payload.participation_id, &submitter_id)?;
+        let judge = retrieve_judge(conn, &submitter_id)?;
+        let participation = retrieve_participation(conn, &payload.participation_id)?;
+        Ok((score, room, judge, participation))
+    }).await?;
 
-    let websocket_response = WebsocketResponse {
-        action: "score submitted".to_owned(),
-        id: payload.participation_id,
-    };
+    publish(&state, &room.id, RoomEvent::ScoreSubmitted {
+        score: score_result.clone(),
+        seat: judge.seat,
+        aggregated: participation.participation.score.is_some(),
+        participation,
+    });
 
-    let _ = state.tx
-        .send(json!(websocket_response).to_string())
-        .expect("unable to send score message");
+    return Ok((StatusCode::CREATED, Json(score_result)).into_response());
+}
+async fn get_judges(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    params: Query<JudgeFilter>
+) -> Result<Response, AppError> {
+    let room_id = params.room_id.clone().ok_or_else(|| AppError::validation("room_id is required"))?;
+    let result = with_connection(&state.pool, move |conn| {
+        let caller = authorize(conn, &token, &room_id, EVERYONE)?;
+        retrieve_judges(conn, caller.tenant(), &room_id)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
 
-    return (StatusCode::CREATED, Json(score_result)).into_response();
+async fn post_judge(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Json(payload): Json<JudgeRequest>
+) -> Result<Response, AppError> {
+    if let Some(room_id) = payload.room_id {
+        let result = with_connection(&state.pool, move |conn| {
+            authorize(conn, &token, &room_id, HOST)?;
+            insert_judge(conn, &room_id, payload.seat, payload.label)
+        }).await?;
+        publish(&state, &result.room_id, RoomEvent::JudgeSeated { judge: result.clone() });
+        return Ok((StatusCode::CREATED, Json(result)).into_response());
+    } else {
+        return Err(AppError::validation("room_id is required"));
+    }
 }
-async fn get_participants(params: Query<ParticipantFilter>) -> Response {
-    let result = retrieve_participants(&mut establish_connection(), &params.room_id);
-    return (StatusCode::OK, Json(result)).into_response();
+
+async fn patch_judge(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<JudgeRequest>
+) -> Result<Response, AppError> {
+    let judge = with_connection(&state.pool, move |conn| {
+        authorize_judge(conn, &token, &id, HOST)?;
+        update_judge(conn, id, payload.label)
+    }).await?;
+    let room_id = judge.room_id.clone();
+    publish(&state, &room_id, RoomEvent::JudgeUpdated { judge });
+    return Ok((StatusCode::OK, "Updated").into_response());
 }
 
-async fn get_scores(params: Query<ScoreFilter>) -> Response {
-    let result = retrieve_scores(
-        &mut establish_connection(),
-        &params.participation_id,
-        &params.submitter_id
-    );
-    return (StatusCode::OK, Json(result)).into_response();
+/// Lets the seat be joined again, e.g. when a judge switches devices.
+async fn release_judge(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let judge = with_connection(&state.pool, move |conn| {
+        authorize_judge(conn, &token, &id, HOST)?;
+        release_judge_seat(conn, &id)
+    }).await?;
+    publish(&state, &judge.room_id, RoomEvent::JudgeUpdated { judge: judge.clone() });
+    return Ok((StatusCode::OK, Json(judge)).into_response());
 }
 
-async fn advance_room(Path(id): Path<String>, Json(payload): Json<Vec<Participant>>) -> Response {
-    let result = create_next_round(&mut establish_connection(), &id, payload);
+async fn delete_judge(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let judge = with_connection(&state.pool, move |conn| {
+        authorize_judge(conn, &token, &id, HOST)?;
+        remove_judge(conn, id)
+    }).await?;
+    publish(&state, &judge.room_id, RoomEvent::JudgeRemoved {
+        judge_id: judge.id.clone(),
+        seat: judge.seat,
+    });
+    return Ok((StatusCode::OK, "Deleted").into_response());
+}
 
-    return (StatusCode::CREATED, Json(result)).into_response();
+async fn get_participants(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    params: Query<ParticipantFilter>
+) -> Result<Response, AppError> {
+    let room_id = params.room_id.clone().ok_or_else(|| AppError::validation("room_id is required"))?;
+    let result = with_connection(&state.pool, move |conn| {
+        let caller = authorize(conn, &token, &room_id, EVERYONE)?;
+        retrieve_participants(conn, caller.tenant(), &room_id)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
 }
 
-async fn get_round(Path(id): Path<String>) -> Response {
-    let result = retrieve_round(&mut establish_connection(), &id);
-    return (StatusCode::OK, Json(result)).into_response();
+async fn get_scores(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    params: Query<ScoreFilter>
+) -> Result<Response, AppError> {
+    let participation_id = params.participation_id
+        .clone()
+        .ok_or_else(|| AppError::validation("participation_id is required"))?;
+    let result = with_connection(&state.pool, move |conn| {
+        let (_, caller) = authorize_participation(conn, &token, &participation_id, EVERYONE)?;
+        retrieve_scores(conn, caller.tenant(), &params.participation_id, &params.submitter_id)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn advance_room(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    params: Query<AdvanceFilter>,
+    payload: Option<Json<Vec<Participant>>>
+) -> Result<Response, AppError> {
+    let strategy = validate_order_strategy(params.order.as_deref().unwrap_or("manual"))?;
+    let (result, round_response) = with_connection(&state.pool, move |conn| {
+        // Coin flips drawn for the cut or the running order only stick if the
+        // round they decide is created too.
+        conn.transaction(|conn| {
+            authorize(conn, &token, &id, HOST)?;
+            let participants = match (params.mode.as_deref(), payload) {
+                (Some("cut"), _) => select_advancing_participants(conn, &id)?,
+                (None | Some("manual"), Some(Json(participants))) => participants,
+                (None | Some("manual"), None) => {
+                    return Err(AppError::validation("manual mode needs a list of participants"));
+                }
+                (Some(mode), _) => {
+                    return Err(
+                        AppError::validation(format!("mode must be one of manual, cut (got {})", mode))
+                    );
+                }
+            };
+            let (participants, seed) = order_participants(
+                conn,
+                &id,
+                participants,
+                strategy,
+                params.seed
+            )?;
+            let round = create_next_round(conn, &id, participants, strategy, seed)?;
+            let round_response = retrieve_round(conn, &round.id)?;
+            Ok((round, round_response))
+        })
+    }).await?;
+
+    publish(&state, &result.room_id, RoomEvent::RoundAdvanced { round: round_response });
+
+    return Ok((StatusCode::CREATED, Json(result)).into_response());
+}
+
+async fn get_round(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        authorize_round(conn, &token, &id, EVERYONE)?;
+        retrieve_round(conn, &id)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn get_standings(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        authorize_round(conn, &token, &id, EVERYONE)?;
+        retrieve_round_standings(conn, &id, CoinFlips::Keep)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+/// Settles the round's unresolved coin-flip ties. Draws already made are kept.
+async fn post_standings_coin_flip(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        authorize_round(conn, &token, &id, HOST)?;
+        conn.transaction(|conn| retrieve_round_standings(conn, &id, CoinFlips::Draw))
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn get_leaderboard(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, EVERYONE)?;
+        retrieve_room_leaderboard(conn, &id, CoinFlips::Keep)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+/// Settles the leaderboard's unresolved coin-flip ties. Draws already made are kept.
+async fn post_leaderboard_coin_flip(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        conn.transaction(|conn| retrieve_room_leaderboard(conn, &id, CoinFlips::Draw))
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn post_participation(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<ParticipationCreateRequest>
+) -> Result<Response, AppError> {
+    let (result, room, participation) = with_connection(&state.pool, move |conn| {
+        let (room, _) = authorize_round(conn, &token, &id, HOST)?;
+        let result = insert_participation(
+            conn,
+            &id,
+            &payload.participant_id,
+            payload.competitive,
+            payload.performance_order
+        )?;
+        let participation = retrieve_participation(conn, &result.id)?;
+        Ok((result, room, participation))
+    }).await?;
+
+    publish(&state, &room.id, RoomEvent::ParticipationAdded { participation });
+
+    return Ok((StatusCode::CREATED, Json(result)).into_response());
 }
 
 async fn patch_participation(
     State(state): State<Arc<AppState>>,
+    token: AccessToken,
     Path(id): Path<String>,
     Json(payload): Json<ParticipationRequest>
-) -> Response {
-    let id_value = id.to_string();
-    update_participation(&mut establish_connection(), id, payload.notes, payload.length);
+) -> Result<Response, AppError> {
+    let (room, participation) = with_connection(&state.pool, move |conn| {
+        let (room, caller) = authorize_participation(conn, &token, &id, TIMEKEEPERS)?;
+        if caller.role == Role::Timekeeper && payload.competitive.is_some() {
+            return Err(
+                AppError::Forbidden("timekeepers may only change a performance's length and notes".to_owned())
+            );
+        }
+        let updated = update_participation(
+            conn,
+            id,
+            payload.notes,
+            payload.length,
+            payload.competitive
+        )?;
+        let participation = retrieve_participation(conn, &updated.id)?;
+        Ok((room, participation))
+    }).await?;
+
+    publish(&state, &room.id, RoomEvent::ParticipationUpdated { participation });
+
+    return Ok((StatusCode::OK, "Updated").into_response());
+}
+
+async fn start_participation_timer(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let (room, participation) = apply_timer(&state, token, id, start_timer).await?;
+    if let Some(started_at) = &participation.participation.timer_started_at {
+        state.timers.start(
+            state.channels.clone(),
+            &room.id,
+            &participation.participant.id,
+            &participation.participation.id,
+            parse_timestamp(started_at)?
+        );
+    }
+    publish(&state, &room.id, RoomEvent::TimerStarted { participation: participation.clone() });
+    return Ok((StatusCode::OK, Json(participation)).into_response());
+}
+
+async fn stop_participation_timer(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let (room, participation) = apply_timer(&state, token, id, stop_timer).await?;
+    state.timers.stop(&participation.participation.id);
+    publish(&state, &room.id, RoomEvent::TimerStopped { participation: participation.clone() });
+    return Ok((StatusCode::OK, Json(participation)).into_response());
+}
+
+async fn reset_participation_timer(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let (room, participation) = apply_timer(&state, token, id, reset_timer).await?;
+    state.timers.stop(&participation.participation.id);
+    publish(&state, &room.id, RoomEvent::TimerReset { participation: participation.clone() });
+    return Ok((StatusCode::OK, Json(participation)).into_response());
+}
+
+/// Runs one timer action and loads the room to tell about it.
+async fn apply_timer(
+    state: &AppState,
+    token: AccessToken,
+    id: String,
+    action: fn(&mut PgConnection, &str) -> Result<Participation, AppError>
+) -> Result<(Room, ParticipationResponse), AppError> {
+    return with_connection(&state.pool, move |conn| {
+        let (room, _) = authorize_participation(conn, &token, &id, TIMEKEEPERS)?;
+        let updated = action(conn, &id)?;
+        let participation = retrieve_participation(conn, &updated.id)?;
+        Ok((room, participation))
+    }).await;
+}
+
+async fn current_room(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, EVERYONE)?;
+        retrieve_current_round(conn, &id)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn put_current(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<CurrentPerformerRequest>
+) -> Result<Response, AppError> {
+    let room_id = id.clone();
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        update_current_participation(conn, &id, Some(&payload.participation_id))
+    }).await?;
+    return Ok(performer_changed(&state, &room_id, result));
+}
+
+async fn delete_current(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let room_id = id.clone();
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        update_current_participation(conn, &id, None)
+    }).await?;
+    return Ok(performer_changed(&state, &room_id, result));
+}
+
+async fn next_performer(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let room_id = id.clone();
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        step_current_participation(conn, &id, PerformerStep::Next)
+    }).await?;
+    return Ok(performer_changed(&state, &room_id, result));
+}
 
-    let websocket_response = WebsocketResponse {
-        action: "deduction submitted".to_owned(),
-        id: id_value,
+async fn previous_performer(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let room_id = id.clone();
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        step_current_participation(conn, &id, PerformerStep::Previous)
+    }).await?;
+    return Ok(performer_changed(&state, &room_id, result));
+}
+
+/// Tells the room who is on stage now and answers the host with the same view.
+fn performer_changed(state: &AppState, room_id: &str, result: CurrentRoundResponse) -> Response {
+    publish(state, room_id, RoomEvent::CurrentPerformerChanged {
+        round_id: Some(result.round.round.id.clone()),
+        participation: result.current_participation.clone(),
+    });
+    return (StatusCode::OK, Json(result)).into_response();
+}
+
+async fn get_room_time_penalty(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, EVERYONE)?;
+        retrieve_time_penalty(conn, &id, None)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn put_room_time_penalty(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<TimePenaltyRequest>
+) -> Result<Response, AppError> {
+    let settings = validate_time_penalty(&payload)?;
+    let room_id = id.clone();
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &room_id, HOST)?;
+        upsert_time_penalty(conn, &room_id, None, &settings)
+    }).await?;
+
+    publish(&state, &id, RoomEvent::TimePenaltyUpdated {
+        round_id: None,
+        settings: TimePenaltySettings::from(&result),
+    });
+
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn get_round_time_penalty(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        let (room, _) = authorize_round(conn, &token, &id, EVERYONE)?;
+        retrieve_time_penalty(conn, &room.id, Some(&id))
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn put_round_time_penalty(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<TimePenaltyRequest>
+) -> Result<Response, AppError> {
+    let settings = validate_time_penalty(&payload)?;
+    let result = with_connection(&state.pool, move |conn| {
+        let (room, _) = authorize_round(conn, &token, &id, HOST)?;
+        upsert_time_penalty(conn, &room.id, Some(&id), &settings)
+    }).await?;
+
+    publish(&state, &result.room_id, RoomEvent::TimePenaltyUpdated {
+        round_id: result.round_id.clone(),
+        settings: TimePenaltySettings::from(&result),
+    });
+
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn delete_round_time_penalty(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let round_id = id.clone();
+    let (room, settings) = with_connection(&state.pool, move |conn| {
+        let (room, _) = authorize_round(conn, &token, &id, HOST)?;
+        remove_round_time_penalty(conn, &id)?;
+        let settings = retrieve_time_penalty(conn, &room.id, Some(&id))?;
+        Ok((room, settings))
+    }).await?;
+
+    publish(&state, &room.id, RoomEvent::TimePenaltyUpdated {
+        round_id: Some(round_id),
+        settings,
+    });
+
+    return Ok((StatusCode::OK, "Deleted").into_response());
+}
+
+async fn get_credentials(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        retrieve_credentials(conn, &id)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn post_credential(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<CredentialRequest>
+) -> Result<Response, AppError> {
+    let role = validate_role(payload.role.as_deref().ok_or_else(|| AppError::validation("role is required"))?)?;
+    let result = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        insert_credential(conn, &id, role, payload.judge_id, payload.label)
+    }).await?;
+    return Ok((StatusCode::CREATED, Json(result)).into_response());
+}
+
+async fn delete_credential(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    with_connection(&state.pool, move |conn| {
+        authorize_credential(conn, &token, &id, HOST)?;
+        remove_credential(conn, id)
+    }).await?;
+    return Ok((StatusCode::OK, "Deleted").into_response());
+}
+
+async fn get_join_code(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let room = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        Ok(retrieve_room(conn, &id)?.room)
+    }).await?;
+    return Ok((StatusCode::OK, Json(JoinCodeResponse { room_id: room.id, join_code: room.join_code })).into_response());
+}
+
+async fn rotate_join_code(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let room = with_connection(&state.pool, move |conn| {
+        authorize(conn, &token, &id, HOST)?;
+        update_join_code(conn, &id)
+    }).await?;
+    return Ok((StatusCode::OK, Json(JoinCodeResponse { room_id: room.id, join_code: room.join_code })).into_response());
+}
+
+async fn get_join(
+    State(state): State<Arc<AppState>>,
+    Path(code): Path<String>
+) -> Result<Response, AppError> {
+    let room = with_connection(&state.pool, move |conn| retrieve_room_by_join_code(conn, &code)).await?;
+    let result = JoinRoomResponse { room_id: room.id, name: room.name, judge_count: room.judge_count };
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn post_join(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<JoinRequest>
+) -> Result<Response, AppError> {
+    let (Some(code), Some(seat)) = (payload.code, payload.seat) else {
+        return Err(AppError::validation("code and seat are required"));
     };
+    let judge_tokens = state.judge_tokens.clone();
+    let result = with_connection(&state.pool, move |conn| issue_judge_token(conn, &judge_tokens, &code, seat)).await?;
+    publish(&state, &result.room_id, RoomEvent::JudgeUpdated { judge: result.judge.clone() });
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn post_organizer(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<OrganizerRequest>
+) -> Result<Response, AppError> {
+    let email = validate_email(payload.email.as_deref().ok_or_else(|| AppError::validation("email is required"))?)?;
+    let name = payload.name
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| AppError::validation("name is required"))?;
+    let password = payload.password.ok_or_else(|| AppError::validation("password is required"))?;
+    validate_password(&password)?;
+    // Registering also signs the new organizer in.
+    let result = with_connection(&state.pool, move |conn| {
+        let password_hash = hash_password(&password)?;
+        conn.transaction(|conn| {
+            let organizer = insert_organizer(conn, &email, &name, password_hash)?;
+            insert_session(conn, organizer)
+        })
+    }).await?;
+    return Ok((StatusCode::CREATED, Json(result)).into_response());
+}
 
-    let _ = state.tx
-        .send(json!(websocket_response).to_string())
-        .expect("unable to send score message");
+async fn get_session(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken
+) -> Result<Response, AppError> {
+    let organizer = with_connection(&state.pool, move |conn| authenticate_organizer(conn, token.value())).await?;
+    return Ok((StatusCode::OK, Json(organizer)).into_response());
+}
 
-    return (StatusCode::OK, "Updated").into_response();
+async fn post_session(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<LoginRequest>
+) -> Result<Response, AppError> {
+    let (Some(email), Some(password)) = (payload.email, payload.password) else {
+        return Err(AppError::validation("email and password are required"));
+    };
+    let email = email.trim().to_lowercase();
+    let result = with_connection(&state.pool, move |conn| sign_in(conn, &email, &password)).await?;
+    return Ok((StatusCode::CREATED, Json(result)).into_response());
 }
 
-async fn current_room(Path(id): Path<String>) -> Response {
-    let room_result = retrieve_room(&mut establish_connection(), id.as_str());
-    if let Some(round_id) = room_result.room.round_id_current {
-        let result = retrieve_round(&mut establish_connection(), &round_id);
-        return (StatusCode::OK, Json(result)).into_response();
-    } else {
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-    }
-}
\ No newline at end of file
+async fn delete_session(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken
+) -> Result<Response, AppError> {
+    with_connection(&state.pool, move |conn| remove_session(conn, token.value())).await?;
+    return Ok((StatusCode::OK, "Signed out").into_response());
+}
+
+async fn get_organizations(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        let organizer = authenticate_organizer(conn, token.value())?;
+        retrieve_organizations(conn, &organizer.id)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn post_organization(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Json(payload): Json<OrganizationRequest>
+) -> Result<Response, AppError> {
+    let name = validate_organization_name(payload.name)?;
+    let result = with_connection(&state.pool, move |conn| {
+        let organizer = authenticate_organizer(conn, token.value())?;
+        insert_organization(conn, &name, &organizer.id)
+    }).await?;
+    return Ok((StatusCode::CREATED, Json(result)).into_response());
+}
+
+async fn get_organization(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        let member = authorize_member(conn, token.value(), &id, ORG_MEMBERS)?;
+        retrieve_organization(conn, &member)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn patch_organization(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<OrganizationRequest>
+) -> Result<Response, AppError> {
+    let name = validate_organization_name(payload.name)?;
+    let result = with_connection(&state.pool, move |conn| {
+        authorize_member(conn, token.value(), &id, ORG_OWNERS)?;
+        update_organization(conn, &id, &name)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+fn validate_organization_name(name: Option<String>) -> Result<String, AppError> {
+    return name
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| AppError::validation("name is required"));
+}
+
+async fn get_members(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    let result = with_connection(&state.pool, move |conn| {
+        authorize_member(conn, token.value(), &id, ORG_MEMBERS)?;
+        retrieve_members(conn, &id)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+async fn post_member(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<MemberRequest>
+) -> Result<Response, AppError> {
+    let email = validate_email(payload.email.as_deref().ok_or_else(|| AppError::validation("email is required"))?)?;
+    let role = validate_member_role(payload.role.as_deref().unwrap_or(MemberRole::Organizer.as_str()))?;
+    let result = with_connection(&state.pool, move |conn| {
+        authorize_member(conn, token.value(), &id, ORG_OWNERS)?;
+        insert_member(conn, &id, &email, role)
+    }).await?;
+    return Ok((StatusCode::CREATED, Json(result)).into_response());
+}
+
+async fn patch_member(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>,
+    Json(payload): Json<MemberRequest>
+) -> Result<Response, AppError> {
+    let role = validate_member_role(payload.role.as_deref().ok_or_else(|| AppError::validation("role is required"))?)?;
+    let result = with_connection(&state.pool, move |conn| {
+        authorize_membership(conn, token.value(), &id, ORG_OWNERS)?;
+        update_member_role(conn, &id, role)
+    }).await?;
+    return Ok((StatusCode::OK, Json(result)).into_response());
+}
+
+/// Owners remove members; anyone may leave on their own.
+async fn delete_member(
+    State(state): State<Arc<AppState>>,
+    token: AccessToken,
+    Path(id): Path<String>
+) -> Result<Response, AppError> {
+    with_connection(&state.pool, move |conn| {
+        let (member, caller) = authorize_membership(conn, token.value(), &id, ORG_MEMBERS)?;
+        if caller.id != member.id && caller.role() != MemberRole::Owner {
+            return Err(AppError::Forbidden(format!("the {} role is not allowed to do this", caller.role().as_str())));
+        }
+        remove_member(conn, &id)
+    }).await?;
+    return Ok((StatusCode::OK, "Deleted").into_response());
+}
@@ -1,5 +1,5 @@
 use serde::{ Deserialize, Serialize };
-use crate::schema::*;
+use crate::{ schema::*, scoring::{ from_fixed, net_score, TimePenaltySettings } };
 use diesel::{ Insertable, Queryable, AsChangeset, Identifiable, Associations, Selectable };
 
 // Tables
@@ -12,6 +12,21 @@ pub struct Room {
     pub created: String,
     pub round_id_current: Option<String>,
     pub participation_id_current: Option<String>,
+    pub judge_count: i32,
+    pub drop_count: i32,
+    pub aggregation_mode: String,
+    pub tie_break_method: String,
+    pub cut_sizes: Vec<i32>,
+    pub score_min: f32,
+    pub score_max: f32,
+    pub score_precision: i32,
+    /// Lets judges join the room, so it is only ever shown to hosts.
+    #[serde(skip_serializing)]
+    pub join_code: String,
+    /// Who created the room. What they may do with it depends on their
+    /// membership in `organization_id`.
+    pub organizer_id: Option<String>,
+    pub organization_id: Option<String>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable, Associations)]
 #[diesel(belongs_to(Room))]
@@ -21,13 +36,97 @@ pub struct Participant {
     pub name: String,
     pub pronouns: Option<String>,
     pub room_id: String,
+    pub organization_id: Option<String>,
+    /// `false` for a sacrificial poet: left out when a cut picks the first
+    /// round's field, and entered as non-competitive in any round they join.
+    #[serde(default = "competitive_by_default")]
+    pub competitive: bool,
+}
+
+fn competitive_by_default() -> bool {
+    return true;
+}
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Room))]
+#[diesel(table_name = judges)]
+pub struct Judge {
+    pub id: String,
+    pub room_id: String,
+    pub seat: i32,
+    pub label: String,
+    pub organization_id: Option<String>,
+    /// When a judge last joined the seat with the join code. A claimed seat
+    /// can't be joined again until the host releases it.
+    pub claimed_at: Option<String>,
+    /// Identifies the seat's current token; replacing it revokes the old one.
+    #[serde(skip_serializing)]
+    pub token_nonce: Option<String>,
+}
+/// Someone who runs events, in whichever organizations they are a member of.
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable)]
+#[diesel(table_name = organizers)]
+pub struct Organizer {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub created: String,
+}
+/// A venue or collective, and the tenant everything in its rooms belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable)]
+#[diesel(table_name = organizations)]
+pub struct Organization {
+    pub id: String,
+    pub name: String,
+    pub created: String,
+}
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Organization))]
+#[diesel(belongs_to(Organizer))]
+#[diesel(table_name = organization_members)]
+pub struct OrganizationMember {
+    pub id: String,
+    pub organization_id: String,
+    pub organizer_id: String,
+    pub role: String,
+    pub created: String,
+}
+/// A signed-in organizer. Like credentials, only the token's hash is stored.
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Organizer))]
+#[diesel(table_name = organizer_sessions)]
+pub struct OrganizerSession {
+    pub id: String,
+    pub organizer_id: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub created: String,
+    pub expires_at: String,
+}
+/// A bearer token's grant in one room. The token itself is only shown when it
+/// is issued; the database keeps its hash.
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Room))]
+#[diesel(table_name = room_credentials)]
+pub struct RoomCredential {
+    pub id: String,
+    pub room_id: String,
+    pub role: String,
+    pub judge_id: Option<String>,
+    pub label: Option<String>,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub created: String,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable, Associations)]
 #[diesel(belongs_to(Participation))]
 #[diesel(table_name = scores)]
 pub struct Score {
     pub id: String,
-    pub value: f32,
+    /// In thousandths of a point.
+    #[serde(with = "crate::scoring::fixed_point")]
+    pub value: i32,
     pub submitter_id: Option<String>,
     pub participation_id: String,
 }
@@ -38,6 +137,8 @@ pub struct Round {
     pub id: String,
     pub round_number: i32,
     pub room_id: String,
+    pub order_strategy: String,
+    pub order_seed: Option<i64>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable, Associations)]
 #[diesel(belongs_to(Round))]
@@ -48,34 +149,157 @@ pub struct Participation {
     pub performance_notes: Option<String>,
     pub performance_length_in_seconds: Option<i32>,
     pub deduction: Option<f32>,
-    pub score: Option<f32>,
+    /// The aggregated judge score, in thousandths of a point.
+    #[serde(with = "crate::scoring::fixed_point::option")]
+    pub score: Option<i32>,
     pub performance_order: i32,
     pub round_id: String,
     pub participant_id: String,
+    pub competitive: bool,
+    pub timer_started_at: Option<String>,
+    pub timer_stopped_at: Option<String>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Room))]
+#[diesel(table_name = time_penalty_policies)]
+pub struct TimePenaltyPolicy {
+    pub id: String,
+    pub room_id: String,
+    pub round_id: Option<String>,
+    pub time_limit_seconds: i32,
+    pub grace_seconds: i32,
+    pub increment_seconds: i32,
+    pub penalty_per_increment: f32,
+    pub penalty_cap: Option<f32>,
+    pub no_penalty: bool,
+}
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Selectable, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Room))]
+#[diesel(belongs_to(Participant))]
+#[diesel(table_name = coin_flips)]
+pub struct CoinFlip {
+    pub id: String,
+    pub room_id: String,
+    pub scope_id: String,
+    pub participant_id: String,
+    pub draw: i32,
+    pub created: String,
 }
 
 // Requests
 
+/// The only messages a websocket client may send. Anything that doesn't parse
+/// as one of these is rejected rather than passed on to the room.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum ClientCommand {
+    Ping,
+    Subscribe {
+        room_id: String,
+        epoch: Option<String>,
+        last_seq: Option<u64>,
+    },
+    Announce {
+        message: String,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoomRequest {
     pub name: Option<String>,
+    pub judge_count: Option<i32>,
+    pub drop_count: Option<i32>,
+    pub aggregation_mode: Option<String>,
+    pub tie_break_method: Option<String>,
+    pub cut_sizes: Option<Vec<i32>>,
+    pub score_min: Option<f32>,
+    pub score_max: Option<f32>,
+    pub score_precision: Option<i32>,
+    /// Only read when creating a room; rooms never move between organizations.
+    pub organization_id: Option<String>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParticipantRequest {
     pub name: Option<String>,
     pub pronouns: Option<String>,
     pub room_id: Option<String>,
+    pub competitive: Option<bool>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JudgeRequest {
+    pub room_id: Option<String>,
+    pub seat: Option<i32>,
+    pub label: Option<String>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScoreRequest {
     pub value: f32,
     pub participation_id: String,
-    pub submitter_id: String
+    /// Only read from hosts scoring on a judge's behalf; judges score as themselves.
+    pub submitter_id: Option<String>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParticipationRequest {
     pub notes: Option<String>,
     pub length: Option<i32>,
+    pub competitive: Option<bool>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParticipationCreateRequest {
+    pub participant_id: String,
+    pub competitive: Option<bool>,
+    pub performance_order: Option<i32>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialRequest {
+    pub role: Option<String>,
+    pub judge_id: Option<String>,
+    pub label: Option<String>,
+}
+/// Claims a room that no organization owns yet. The host token proves the
+/// room is the claimant's; the organization defaults as for a new room.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomClaimRequest {
+    pub host_token: Option<String>,
+    pub organization_id: Option<String>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganizerRequest {
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub password: Option<String>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginRequest {
+    pub email: Option<String>,
+    pub password: Option<String>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganizationRequest {
+    pub name: Option<String>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberRequest {
+    pub email: Option<String>,
+    pub role: Option<String>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinRequest {
+    pub code: Option<String>,
+    pub seat: Option<i32>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrentPerformerRequest {
+    pub participation_id: String,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimePenaltyRequest {
+    pub time_limit_seconds: Option<i32>,
+    pub grace_seconds: Option<i32>,
+    pub increment_seconds: Option<i32>,
+    pub penalty_per_increment: Option<f32>,
+    pub penalty_cap: Option<f32>,
+    pub no_penalty: Option<bool>,
 }
 
 // Updates
@@ -84,12 +308,27 @@ pub struct ParticipationRequest {
 pub struct ParticipantUpdate {
     pub name: Option<String>,
     pub pronouns: Option<String>,
+    pub competitive: Option<bool>,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = judges)]
+pub struct JudgeUpdate {
+    pub label: Option<String>,
 }
 
 #[derive(AsChangeset)]
 #[diesel(table_name = rooms)]
 pub struct RoomUpdate {
     pub name: Option<String>,
+    pub judge_count: Option<i32>,
+    pub drop_count: Option<i32>,
+    pub aggregation_mode: Option<String>,
+    pub tie_break_method: Option<String>,
+    pub cut_sizes: Option<Vec<i32>>,
+    pub score_min: Option<f32>,
+    pub score_max: Option<f32>,
+    pub score_precision: Option<i32>,
 }
 
 #[derive(AsChangeset)]
@@ -98,7 +337,8 @@ pub struct ParticipationUpdate {
     pub performance_notes: Option<String>,
     pub performance_length_in_seconds: Option<i32>,
     pub deduction: Option<f32>,
-    pub score: Option<f32>,
+    pub score: Option<i32>,
+    pub competitive: Option<bool>,
 }
 
 
@@ -118,26 +358,330 @@ pub struct RoundResponse {
     pub participations: Vec<ParticipationResponse>
 }
 
+/// A newly issued credential, the only time its token is ever sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialResponse {
+    #[serde(flatten)]
+    pub credential: RoomCredential,
+    pub token: String,
+}
+
+/// A new room and the host credential that controls it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedRoomResponse {
+    #[serde(flatten)]
+    pub room: Room,
+    pub join_code: String,
+    pub host_credential: CredentialResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinCodeResponse {
+    pub room_id: String,
+    pub join_code: String,
+}
+
+/// A new session, the only time its token is ever sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub token: String,
+    pub expires_at: String,
+    pub organizer: Organizer,
+}
+
+/// An organization and the signed-in organizer's role in it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganizationResponse {
+    #[serde(flatten)]
+    pub organization: Organization,
+    pub role: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberResponse {
+    #[serde(flatten)]
+    pub member: OrganizationMember,
+    pub organizer: Organizer,
+}
+
+/// What anyone holding a join code may learn before joining.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinRoomResponse {
+    pub room_id: String,
+    pub name: String,
+    pub judge_count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JudgeTokenResponse {
+    pub token: String,
+    pub expires_at: String,
+    pub room_id: String,
+    pub judge: Judge,
+}
+
+/// The room's current round, plus whoever is on stage in it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrentRoundResponse {
+    #[serde(flatten)]
+    pub round: RoundResponse,
+    pub current_participation: Option<ParticipationResponse>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParticipationResponse {
     pub participation: Participation,
-    pub participant: Participant
+    pub participant: Participant,
+    pub raw_score: Option<f32>,
+    pub deduction: f32,
+    pub net_score: Option<f32>,
+}
+
+impl ParticipationResponse {
+    pub fn new(participation: Participation, participant: Participant) -> ParticipationResponse {
+        let raw_score = participation.score.map(|score| from_fixed(i64::from(score)));
+        let deduction = participation.deduction.unwrap_or_default();
+        return ParticipationResponse {
+            participation,
+            participant,
+            raw_score,
+            deduction,
+            net_score: net_score(raw_score, deduction),
+        };
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Standing {
+    pub rank: Option<i32>,
+    pub tied: bool,
+    #[serde(flatten)]
+    pub participation: ParticipationResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StandingsResponse {
+    pub round: Round,
+    pub tie_break_method: String,
+    pub standings: Vec<Standing>,
+    pub tie_breaks: Vec<TieBreakResolution>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieBreakValue {
+    pub participant_id: String,
+    pub value: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieBreakStep {
+    pub method: String,
+    pub values: Vec<TieBreakValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieBreakResolution {
+    pub rank: i32,
+    pub participant_ids: Vec<String>,
+    pub steps: Vec<TieBreakStep>,
+    pub resolved: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundScore {
+    pub round_id: String,
+    pub round_number: i32,
+    pub participation_id: String,
+    pub net_score: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub rank: Option<i32>,
+    pub tied: bool,
+    pub participant: Participant,
+    pub rounds: Vec<RoundScore>,
+    /// Sum of the net scores, in thousandths of a point.
+    #[serde(with = "crate::scoring::fixed_point::wide")]
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardResponse {
+    pub room: Room,
+    pub rounds: Vec<Round>,
+    pub leaderboard: Vec<LeaderboardEntry>,
+    pub tie_breaks: Vec<TieBreakResolution>,
+}
+
+pub const EVENT_VERSION: u32 = 1;
+
+/// Everything pushed to a room's live clients. Serialized as
+/// `{ "type": "score_submitted", "payload": { ... } }` so clients can switch on
+/// `type` and update in place instead of refetching.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum RoomEvent {
+    ScoreSubmitted {
+        score: Score,
+        seat: i32,
+        aggregated: bool,
+        participation: ParticipationResponse,
+    },
+    ParticipationAdded {
+        participation: ParticipationResponse,
+    },
+    ParticipationUpdated {
+        participation: ParticipationResponse,
+    },
+    RoundAdvanced {
+        round: RoundResponse,
+    },
+    TimerStarted {
+        participation: ParticipationResponse,
+    },
+    /// Sent every second while a timer runs. Ticks are transient: they are not
+    /// numbered or replayed to reconnecting clients.
+    TimerTick {
+        participation_id: String,
+        elapsed_seconds: i32,
+    },
+    /// `participation` carries the measured length and the deduction it earned.
+    TimerStopped {
+        participation: ParticipationResponse,
+    },
+    TimerReset {
+        participation: ParticipationResponse,
+    },
+    /// `participation` is `None` when the host clears the stage.
+    CurrentPerformerChanged {
+        round_id: Option<String>,
+        participation: Option<ParticipationResponse>,
+    },
+    RoomRenamed {
+        name: String,
+    },
+    RoomUpdated {
+        room: Room,
+    },
+    RoomDeleted {
+        room_id: String,
+    },
+    ParticipantAdded {
+        participant: Participant,
+    },
+    ParticipantUpdated {
+        participant: Participant,
+    },
+    ParticipantRemoved {
+        participant_id: String,
+    },
+    JudgeSeated {
+        judge: Judge,
+    },
+    JudgeUpdated {
+        judge: Judge,
+    },
+    JudgeRemoved {
+        judge_id: String,
+        seat: i32,
+    },
+    TimePenaltyUpdated {
+        round_id: Option<String>,
+        settings: TimePenaltySettings,
+    },
+    Announcement {
+        message: String,
+    },
+    /// Sent to a single reconnecting client whose missed events have already
+    /// dropped out of the room's history.
+    Snapshot {
+        room: RoomResponse,
+        current_round: Option<RoundResponse>,
+    },
 }
 
+/// Messages sent only to one websocket client: replies to its commands, and
+/// the error it is disconnected with.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WebsocketResponse {
-    pub action: String,
-    pub id: String
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum CommandReply {
+    Pong,
+    Accepted,
+    Subscribed {
+        room_id: String,
+        epoch: String,
+        seq: u64,
+    },
+    Rejected {
+        error: String,
+        message: String,
+    },
+    /// Sent just before the server closes the socket.
+    Error {
+        error: String,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventMessage {
+    pub version: u32,
+    pub room_id: String,
+    pub epoch: String,
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: RoomEvent,
+}
+
+impl EventMessage {
+    pub fn new(room_id: &str, epoch: &str, seq: u64, event: RoomEvent) -> EventMessage {
+        return EventMessage {
+            version: EVENT_VERSION,
+            room_id: room_id.to_owned(),
+            epoch: epoch.to_owned(),
+            seq,
+            event,
+        };
+    }
 }
 
 // Filter
 
+#[derive(Serialize, Deserialize)]
+pub struct RoomFilter {
+    pub organization_id: Option<String>,
+}
 #[derive(Serialize, Deserialize)]
 pub struct ParticipantFilter {
     pub room_id: Option<String>,
 }
 #[derive(Serialize, Deserialize)]
+pub struct JudgeFilter {
+    pub room_id: Option<String>,
+}
+#[derive(Serialize, Deserialize)]
+pub struct AdvanceFilter {
+    pub mode: Option<String>,
+    pub order: Option<String>,
+    pub seed: Option<i64>,
+}
+#[derive(Serialize, Deserialize)]
 pub struct ScoreFilter {
     pub participation_id: Option<String>,
     pub submitter_id: Option<String>,
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WebsocketFilter {
+    pub room_id: Option<String>,
+    pub epoch: Option<String>,
+    pub last_seq: Option<u64>,
+    pub token: Option<String>,
+}
+#[derive(Serialize, Deserialize)]
+pub struct EventStreamFilter {
+    pub epoch: Option<String>,
+    pub last_seq: Option<u64>,
+    pub token: Option<String>,
+}
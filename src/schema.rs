@@ -1,11 +1,74 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    coin_flips (id) {
+        id -> Text,
+        room_id -> Text,
+        scope_id -> Text,
+        participant_id -> Text,
+        draw -> Int4,
+        created -> Text,
+    }
+}
+
+diesel::table! {
+    judges (id) {
+        id -> Text,
+        room_id -> Text,
+        seat -> Int4,
+        label -> Text,
+        organization_id -> Nullable<Text>,
+        claimed_at -> Nullable<Text>,
+        token_nonce -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    organization_members (id) {
+        id -> Text,
+        organization_id -> Text,
+        organizer_id -> Text,
+        role -> Text,
+        created -> Text,
+    }
+}
+
+diesel::table! {
+    organizations (id) {
+        id -> Text,
+        name -> Text,
+        created -> Text,
+    }
+}
+
+diesel::table! {
+    organizer_sessions (id) {
+        id -> Text,
+        organizer_id -> Text,
+        token_hash -> Text,
+        created -> Text,
+        expires_at -> Text,
+    }
+}
+
+diesel::table! {
+    organizers (id) {
+        id -> Text,
+        email -> Text,
+        name -> Text,
+        password_hash -> Text,
+        created -> Text,
+    }
+}
+
 diesel::table! {
     participants (id) {
         id -> Text,
         name -> Text,
         pronouns -> Nullable<Text>,
         room_id -> Text,
+        organization_id -> Nullable<Text>,
+        competitive -> Bool,
     }
 }
 
@@ -15,10 +78,25 @@ diesel::table! {
         performance_notes -> Nullable<Text>,
         performance_length_in_seconds -> Nullable<Int4>,
         deduction -> Nullable<Float4>,
-        score -> Nullable<Float4>,
+        score -> Nullable<Int4>,
         performance_order -> Int4,
         round_id -> Text,
         participant_id -> Text,
+        competitive -> Bool,
+        timer_started_at -> Nullable<Text>,
+        timer_stopped_at -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    room_credentials (id) {
+        id -> Text,
+        room_id -> Text,
+        role -> Text,
+        judge_id -> Nullable<Text>,
+        label -> Nullable<Text>,
+        token_hash -> Text,
+        created -> Text,
     }
 }
 
@@ -29,6 +107,17 @@ diesel::table! {
         created -> Text,
         round_id_current -> Nullable<Text>,
         participation_id_current -> Nullable<Text>,
+        judge_count -> Int4,
+        drop_count -> Int4,
+        aggregation_mode -> Text,
+        tie_break_method -> Text,
+        cut_sizes -> Array<Int4>,
+        score_min -> Float4,
+        score_max -> Float4,
+        score_precision -> Int4,
+        join_code -> Text,
+        organizer_id -> Nullable<Text>,
+        organization_id -> Nullable<Text>,
     }
 }
 
@@ -37,28 +126,66 @@ diesel::table! {
         id -> Text,
         round_number -> Int4,
         room_id -> Text,
+        order_strategy -> Text,
+        order_seed -> Nullable<Int8>,
     }
 }
 
 diesel::table! {
     scores (id) {
         id -> Text,
-        value -> Float4,
+        value -> Int4,
         submitter_id -> Nullable<Text>,
         participation_id -> Text,
     }
 }
 
+diesel::table! {
+    time_penalty_policies (id) {
+        id -> Text,
+        room_id -> Text,
+        round_id -> Nullable<Text>,
+        time_limit_seconds -> Int4,
+        grace_seconds -> Int4,
+        increment_seconds -> Int4,
+        penalty_per_increment -> Float4,
+        penalty_cap -> Nullable<Float4>,
+        no_penalty -> Bool,
+    }
+}
+
+diesel::joinable!(coin_flips -> participants (participant_id));
+diesel::joinable!(coin_flips -> rooms (room_id));
+diesel::joinable!(judges -> organizations (organization_id));
+diesel::joinable!(judges -> rooms (room_id));
+diesel::joinable!(organization_members -> organizations (organization_id));
+diesel::joinable!(organization_members -> organizers (organizer_id));
+diesel::joinable!(organizer_sessions -> organizers (organizer_id));
+diesel::joinable!(participants -> organizations (organization_id));
 diesel::joinable!(participants -> rooms (room_id));
 diesel::joinable!(participations -> participants (participant_id));
 diesel::joinable!(participations -> rounds (round_id));
+diesel::joinable!(room_credentials -> judges (judge_id));
+diesel::joinable!(room_credentials -> rooms (room_id));
+diesel::joinable!(rooms -> organizations (organization_id));
+diesel::joinable!(rooms -> organizers (organizer_id));
 diesel::joinable!(rooms -> participations (participation_id_current));
 diesel::joinable!(scores -> participations (participation_id));
+diesel::joinable!(time_penalty_policies -> rooms (room_id));
+diesel::joinable!(time_penalty_policies -> rounds (round_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    coin_flips,
+    judges,
+    organization_members,
+    organizations,
+    organizer_sessions,
+    organizers,
     participants,
     participations,
+    room_credentials,
     rooms,
     rounds,
     scores,
+    time_penalty_policies,
 );
@@ -0,0 +1,553 @@
+use serde::{ Deserialize, Serialize };
+use crate::{ error::AppError, models::{ Room, TimePenaltyPolicy, TimePenaltyRequest } };
+
+pub const DEFAULT_JUDGE_COUNT: i32 = 5;
+pub const DEFAULT_DROP_COUNT: i32 = 1;
+pub const DEFAULT_AGGREGATION_MODE: &str = "trimmed_sum";
+
+pub const DEFAULT_SCORE_MIN: f32 = 0.0;
+pub const DEFAULT_SCORE_MAX: f32 = 10.0;
+pub const DEFAULT_SCORE_PRECISION: i32 = 1;
+pub const MAX_SCORE_PRECISION: i32 = 3;
+
+/// Totals are summed in thousandths of a point so aggregation never picks up float drift.
+pub const FIXED_POINT_SCALE: f64 = 1000.0;
+
+pub const DEFAULT_TIME_LIMIT_SECONDS: i32 = 180;
+pub const DEFAULT_GRACE_SECONDS: i32 = 10;
+pub const DEFAULT_INCREMENT_SECONDS: i32 = 10;
+pub const DEFAULT_PENALTY_PER_INCREMENT: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregationMode {
+    Sum,
+    Mean,
+    TrimmedSum,
+}
+
+impl AggregationMode {
+    pub fn parse(value: &str) -> Option<AggregationMode> {
+        return match value {
+            "sum" => Some(AggregationMode::Sum),
+            "mean" => Some(AggregationMode::Mean),
+            "trimmed_sum" => Some(AggregationMode::TrimmedSum),
+            _ => None,
+        };
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            AggregationMode::Sum => "sum",
+            AggregationMode::Mean => "mean",
+            AggregationMode::TrimmedSum => "trimmed_sum",
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoringPolicy {
+    pub judge_count: i32,
+    pub drop_count: i32,
+    pub aggregation_mode: AggregationMode,
+}
+
+impl ScoringPolicy {
+    pub fn from_room(room: &Room) -> ScoringPolicy {
+        return ScoringPolicy {
+            judge_count: room.judge_count,
+            drop_count: room.drop_count,
+            aggregation_mode: AggregationMode::parse(&room.aggregation_mode).unwrap_or(
+                AggregationMode::TrimmedSum
+            ),
+        };
+    }
+}
+
+pub fn to_fixed(value: f32) -> i64 {
+    return ((value as f64) * FIXED_POINT_SCALE).round() as i64;
+}
+
+pub fn from_fixed(value: i64) -> f32 {
+    return ((value as f64) / FIXED_POINT_SCALE) as f32;
+}
+
+/// Serde for scores stored as integer thousandths. Clients keep sending and
+/// receiving plain numbers; the conversion happens at the API edge.
+pub mod fixed_point {
+    use serde::{ de::Error, Deserialize, Deserializer, Serializer };
+    use super::{ from_fixed, to_fixed };
+
+    pub fn serialize<S: Serializer>(value: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_f32(from_fixed(i64::from(*value)));
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+        let value = to_fixed(f32::deserialize(deserializer)?);
+        return i32::try_from(value).map_err(|_| D::Error::custom("score is out of range"));
+    }
+
+    pub mod option {
+        use serde::{ de::Error, Deserialize, Deserializer, Serializer };
+        use super::super::{ from_fixed, to_fixed };
+
+        pub fn serialize<S: Serializer>(value: &Option<i32>, serializer: S) -> Result<S::Ok, S::Error> {
+            return match value {
+                Some(value) => serializer.serialize_some(&from_fixed(i64::from(*value))),
+                None => serializer.serialize_none(),
+            };
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i32>, D::Error> {
+            let value = Option::<f32>::deserialize(deserializer)?;
+            return value
+                .map(|value| i32::try_from(to_fixed(value)).map_err(|_| D::Error::custom("score is out of range")))
+                .transpose();
+        }
+    }
+
+    /// For sums of scores, such as a leaderboard total, which can outgrow an `i32`.
+    pub mod wide {
+        use serde::{ Deserialize, Deserializer, Serializer };
+        use super::super::{ from_fixed, to_fixed };
+
+        pub fn serialize<S: Serializer>(value: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+            return serializer.serialize_f32(from_fixed(*value));
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+            return Ok(to_fixed(f32::deserialize(deserializer)?));
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreRange {
+    pub min: f32,
+    pub max: f32,
+    pub precision: i32,
+}
+
+impl ScoreRange {
+    pub fn from_room(room: &Room) -> ScoreRange {
+        return ScoreRange {
+            min: room.score_min,
+            max: room.score_max,
+            precision: room.score_precision,
+        };
+    }
+}
+
+/// Checks a room's score range. Scores and aggregates are stored as `i32`
+/// thousandths, so the bounds must keep even a full `Sum` of judges' scores
+/// within that.
+pub fn validate_score_range(
+    min: f32,
+    max: f32,
+    precision: i32,
+    policy: &ScoringPolicy
+) -> Result<ScoreRange, AppError> {
+    if !min.is_finite() || !max.is_finite() || min >= max {
+        return Err(AppError::validation("score_min must be below score_max"));
+    }
+    let counted_scores = match policy.aggregation_mode {
+        AggregationMode::Sum => policy.judge_count,
+        AggregationMode::TrimmedSum => policy.judge_count - 2 * policy.drop_count,
+        AggregationMode::Mean => 1,
+    }.max(1);
+    let largest = to_fixed(min.abs().max(max.abs())).saturating_mul(i64::from(counted_scores));
+    if i32::try_from(largest).is_err() {
+        let limit = i64::from(i32::MAX) / i64::from(counted_scores) / (FIXED_POINT_SCALE as i64);
+        return Err(AppError::Validation {
+            message: format!("score_min and score_max must be within ±{} for this scoring policy", limit),
+            details: Some(serde_json::json!({ "field": "score_max" })),
+        });
+    }
+    if !(0..=MAX_SCORE_PRECISION).contains(&precision) {
+        return Err(
+            AppError::validation(
+                format!("score_precision must be between 0 and {}", MAX_SCORE_PRECISION)
+            )
+        );
+    }
+    return Ok(ScoreRange { min, max, precision });
+}
+
+/// Details of the 422 returned for a score outside the room's rules.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreValidationError {
+    pub field: String,
+    pub message: String,
+    pub min: f32,
+    pub max: f32,
+    pub precision: i32,
+}
+
+impl From<ScoreValidationError> for AppError {
+    fn from(error: ScoreValidationError) -> AppError {
+        return AppError::Validation {
+            details: Some(
+                serde_json::json!({
+                    "field": error.field,
+                    "min": error.min,
+                    "max": error.max,
+                    "precision": error.precision,
+                })
+            ),
+            message: error.message,
+        };
+    }
+}
+
+/// Checks a submitted score against the room's range and precision and returns
+/// it rounded to that precision, so 8.299999 is stored as 8.3.
+pub fn validate_score(range: &ScoreRange, value: f32) -> Result<f32, ScoreValidationError> {
+    let reject = |message: String| ScoreValidationError {
+        field: "value".to_owned(),
+        message,
+        min: range.min,
+        max: range.max,
+        precision: range.precision,
+    };
+
+    if !value.is_finite() {
+        return Err(reject("score must be a number".to_owned()));
+    }
+    if value < range.min || value > range.max {
+        return Err(reject(format!("score must be between {} and {}", range.min, range.max)));
+    }
+    let scale = (10_f64).powi(range.precision);
+    let scaled = (value as f64) * scale;
+    if (scaled - scaled.round()).abs() > 1e-4 {
+        return Err(
+            reject(format!("score may have at most {} decimal place(s)", range.precision))
+        );
+    }
+    return Ok((scaled.round() / scale) as f32);
+}
+
+/// Default display label for a seat: 1 is "Judge A", 2 is "Judge B", and so on.
+pub fn judge_label(seat: i32) -> String {
+    if (1..=26).contains(&seat) {
+        return format!("Judge {}", ((b'A' + (seat as u8) - 1) as char));
+    }
+    return format!("Judge {}", seat);
+}
+
+/// Checks a raw policy coming from the room API before it is stored.
+pub fn validate_scoring_policy(
+    judge_count: i32,
+    drop_count: i32,
+    aggregation_mode: &str
+) -> Result<ScoringPolicy, AppError> {
+    let mode = match AggregationMode::parse(aggregation_mode) {
+        Some(mode) => mode,
+        None => {
+            return Err(
+                AppError::validation(
+                    format!("aggregation_mode must be one of sum, mean, trimmed_sum (got {})", aggregation_mode)
+                )
+            );
+        }
+    };
+    if judge_count < 1 {
+        return Err(AppError::validation("judge_count must be at least 1"));
+    }
+    if drop_count < 0 {
+        return Err(AppError::validation("drop_count must not be negative"));
+    }
+    if mode == AggregationMode::TrimmedSum && drop_count * 2 >= judge_count {
+        return Err(AppError::validation("drop_count must leave at least one counted score"));
+    }
+    return Ok(ScoringPolicy { judge_count, drop_count, aggregation_mode: mode });
+}
+
+/// Aggregates a complete set of judge scores, in thousandths. `fixed` must be
+/// sorted ascending.
+pub fn aggregate(policy: &ScoringPolicy, fixed: &[i64]) -> i64 {
+    return match policy.aggregation_mode {
+        AggregationMode::Sum => fixed.iter().sum(),
+        AggregationMode::Mean =>
+            ((fixed.iter().sum::<i64>() as f64) / (fixed.len() as f64)).round() as i64,
+        AggregationMode::TrimmedSum => {
+            let drop = policy.drop_count as usize;
+            fixed
+                .iter()
+                .skip(drop)
+                .take(fixed.len().saturating_sub(drop * 2))
+                .sum()
+        }
+    };
+}
+
+/// The score a poet actually receives. Stays `None` until the judges' scores have been aggregated,
+/// so a length recorded first never shows up as a negative total.
+pub fn net_score(raw_score: Option<f32>, deduction: f32) -> Option<f32> {
+    return raw_score.map(|raw| from_fixed(to_fixed(raw) - to_fixed(deduction)));
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimePenaltySettings {
+    pub time_limit_seconds: i32,
+    pub grace_seconds: i32,
+    pub increment_seconds: i32,
+    pub penalty_per_increment: f32,
+    pub penalty_cap: Option<f32>,
+    pub no_penalty: bool,
+}
+
+impl Default for TimePenaltySettings {
+    fn default() -> TimePenaltySettings {
+        return TimePenaltySettings {
+            time_limit_seconds: DEFAULT_TIME_LIMIT_SECONDS,
+            grace_seconds: DEFAULT_GRACE_SECONDS,
+            increment_seconds: DEFAULT_INCREMENT_SECONDS,
+            penalty_per_increment: DEFAULT_PENALTY_PER_INCREMENT,
+            penalty_cap: None,
+            no_penalty: false,
+        };
+    }
+}
+
+impl From<&TimePenaltyPolicy> for TimePenaltySettings {
+    fn from(policy: &TimePenaltyPolicy) -> TimePenaltySettings {
+        return TimePenaltySettings {
+            time_limit_seconds: policy.time_limit_seconds,
+            grace_seconds: policy.grace_seconds,
+            increment_seconds: policy.increment_seconds,
+            penalty_per_increment: policy.penalty_per_increment,
+            penalty_cap: policy.penalty_cap,
+            no_penalty: policy.no_penalty,
+        };
+    }
+}
+
+/// Builds settings from a request, falling back to the defaults for missing fields.
+pub fn validate_time_penalty(
+    request: &TimePenaltyRequest
+) -> Result<TimePenaltySettings, AppError> {
+    let defaults = TimePenaltySettings::default();
+    let settings = TimePenaltySettings {
+        time_limit_seconds: request.time_limit_seconds.unwrap_or(defaults.time_limit_seconds),
+        grace_seconds: request.grace_seconds.unwrap_or(defaults.grace_seconds),
+        increment_seconds: request.increment_seconds.unwrap_or(defaults.increment_seconds),
+        penalty_per_increment: request.penalty_per_increment.unwrap_or(
+            defaults.penalty_per_increment
+        ),
+        penalty_cap: request.penalty_cap,
+        no_penalty: request.no_penalty.unwrap_or(defaults.no_penalty),
+    };
+    if settings.time_limit_seconds < 0 || settings.grace_seconds < 0 {
+        return Err(
+            AppError::validation("time_limit_seconds and grace_seconds must not be negative")
+        );
+    }
+    if settings.increment_seconds < 1 {
+        return Err(AppError::validation("increment_seconds must be at least 1"));
+    }
+    if settings.penalty_per_increment.is_nan() || settings.penalty_per_increment < 0_f32 {
+        return Err(AppError::validation("penalty_per_increment must not be negative"));
+    }
+    if let Some(cap) = settings.penalty_cap {
+        if cap.is_nan() || cap < 0_f32 {
+            return Err(AppError::validation("penalty_cap must not be negative"));
+        }
+    }
+    return Ok(settings);
+}
+
+/// Deduction for a performance of `length` seconds. Every full increment past the
+/// limit plus grace costs `penalty_per_increment`, up to `penalty_cap`.
+pub fn compute_deduction(settings: &TimePenaltySettings, length: i32) -> f32 {
+    if settings.no_penalty {
+        return 0_f32;
+    }
+    let over = length - settings.time_limit_seconds - settings.grace_seconds;
+    if over <= 0 {
+        return 0_f32;
+    }
+    let increments = (over / settings.increment_seconds) as f32;
+    let deduction = increments * settings.penalty_per_increment;
+    return match settings.penalty_cap {
+        Some(cap) => deduction.min(cap),
+        None => deduction,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(values: &[f32]) -> Vec<i64> {
+        return values
+            .iter()
+            .map(|value| to_fixed(*value))
+            .collect();
+    }
+
+    fn policy(mode: AggregationMode, judge_count: i32, drop_count: i32) -> ScoringPolicy {
+        return ScoringPolicy { judge_count, drop_count, aggregation_mode: mode };
+    }
+
+    #[test]
+    fn sum_adds_every_score() {
+        let total = aggregate(&policy(AggregationMode::Sum, 3, 0), &fixed(&[7.5, 8.0, 9.25]));
+        assert_eq!(total, 24750);
+    }
+
+    #[test]
+    fn trimmed_sum_drops_the_highest_and_lowest() {
+        let total = aggregate(&policy(AggregationMode::TrimmedSum, 5, 1), &fixed(&[7.0, 8.0, 8.25, 8.5, 9.75]));
+        assert_eq!(total, 24750);
+
+        let total = aggregate(&policy(AggregationMode::TrimmedSum, 5, 2), &fixed(&[7.0, 8.0, 8.25, 8.5, 9.75]));
+        assert_eq!(total, 8250);
+    }
+
+    #[test]
+    fn mean_averages_every_score() {
+        let total = aggregate(&policy(AggregationMode::Mean, 4, 0), &fixed(&[7.0, 8.0, 8.5, 9.5]));
+        assert_eq!(total, 8250);
+    }
+
+    #[test]
+    fn scoring_policy_must_leave_a_counted_score() {
+        assert!(validate_scoring_policy(2, 1, "trimmed_sum").is_err());
+        assert!(validate_scoring_policy(0, 0, "sum").is_err());
+        assert!(validate_scoring_policy(5, -1, "sum").is_err());
+        assert!(validate_scoring_policy(5, 1, "median").is_err());
+        assert!(validate_scoring_policy(2, 1, "sum").is_ok());
+        assert_eq!(
+            validate_scoring_policy(3, 1, "trimmed_sum").unwrap(),
+            policy(AggregationMode::TrimmedSum, 3, 1)
+        );
+    }
+
+    #[test]
+    fn deduction_counts_full_increments_past_grace() {
+        let settings = TimePenaltySettings::default();
+        assert_eq!(compute_deduction(&settings, 190), 0.0);
+        assert_eq!(compute_deduction(&settings, 199), 0.0);
+        assert_eq!(compute_deduction(&settings, 200), 0.5);
+        assert_eq!(compute_deduction(&settings, 211), 1.0);
+    }
+
+    #[test]
+    fn deduction_respects_cap_and_no_penalty() {
+        let capped = TimePenaltySettings { penalty_cap: Some(1.0), ..TimePenaltySettings::default() };
+        assert_eq!(compute_deduction(&capped, 400), 1.0);
+
+        let waived = TimePenaltySettings { no_penalty: true, ..TimePenaltySettings::default() };
+        assert_eq!(compute_deduction(&waived, 400), 0.0);
+    }
+
+    #[test]
+    fn time_penalty_fills_in_defaults_and_rejects_negatives() {
+        let request = TimePenaltyRequest {
+            time_limit_seconds: Some(240),
+            grace_seconds: None,
+            increment_seconds: None,
+            penalty_per_increment: None,
+            penalty_cap: None,
+            no_penalty: None,
+        };
+        let settings = validate_time_penalty(&request).unwrap();
+        assert_eq!(settings.time_limit_seconds, 240);
+        assert_eq!(settings.grace_seconds, DEFAULT_GRACE_SECONDS);
+
+        assert!(validate_time_penalty(&TimePenaltyRequest { grace_seconds: Some(-1), ..request.clone() }).is_err());
+        assert!(validate_time_penalty(&TimePenaltyRequest { increment_seconds: Some(0), ..request.clone() }).is_err());
+        assert!(validate_time_penalty(&TimePenaltyRequest { penalty_cap: Some(-0.5), ..request }).is_err());
+    }
+
+    #[test]
+    fn net_score_waits_for_the_raw_score() {
+        assert_eq!(net_score(None, 0.5), None);
+        assert_eq!(net_score(Some(25.5), 1.0), Some(24.5));
+        assert_eq!(net_score(Some(25.5), 0.0), Some(25.5));
+    }
+
+    #[test]
+    fn judge_labels_use_letters_then_numbers() {
+        assert_eq!(judge_label(1), "Judge A");
+        assert_eq!(judge_label(26), "Judge Z");
+        assert_eq!(judge_label(27), "Judge 27");
+    }
+
+    #[test]
+    fn aggregation_does_not_pick_up_float_drift() {
+        let total = aggregate(&policy(AggregationMode::Sum, 5, 0), &fixed(&[8.1, 8.2, 8.3, 8.4, 8.5]));
+        assert_eq!(total, 41500);
+        assert_eq!(to_fixed(0.1 + 0.2), 300);
+    }
+
+    #[test]
+    fn mean_rounds_to_the_nearest_thousandth() {
+        assert_eq!(aggregate(&policy(AggregationMode::Mean, 3, 0), &fixed(&[8.0, 8.0, 9.0])), 8333);
+        assert_eq!(aggregate(&policy(AggregationMode::Mean, 3, 0), &fixed(&[8.0, 9.0, 9.0])), 8667);
+    }
+
+    #[test]
+    fn score_range_rejects_inverted_bounds_and_bad_precision() {
+        let sum = policy(AggregationMode::Sum, 5, 0);
+        assert!(validate_score_range(10.0, 0.0, 1, &sum).is_err());
+        assert!(validate_score_range(5.0, 5.0, 1, &sum).is_err());
+        assert!(validate_score_range(0.0, f32::INFINITY, 1, &sum).is_err());
+        assert!(validate_score_range(0.0, 10.0, -1, &sum).is_err());
+        assert!(validate_score_range(0.0, 10.0, MAX_SCORE_PRECISION + 1, &sum).is_err());
+        assert_eq!(
+            validate_score_range(0.0, 10.0, 2, &sum).unwrap(),
+            ScoreRange { min: 0.0, max: 10.0, precision: 2 }
+        );
+    }
+
+    #[test]
+    fn score_outside_the_range_is_rejected() {
+        let range = ScoreRange { min: 0.0, max: 10.0, precision: 1 };
+        let error = validate_score(&range, 10.1).unwrap_err();
+        assert_eq!(error.field, "value");
+        assert_eq!(error.max, 10.0);
+        assert!(validate_score(&range, -0.1).is_err());
+        assert!(validate_score(&range, f32::NAN).is_err());
+        assert_eq!(validate_score(&range, 10.0).unwrap(), 10.0);
+    }
+
+    #[test]
+    fn score_precision_is_enforced_and_rounded() {
+        let range = ScoreRange { min: 0.0, max: 10.0, precision: 1 };
+        assert!(validate_score(&range, 8.25).is_err());
+        assert_eq!(validate_score(&range, 8.299999).unwrap(), 8.3);
+
+        let whole = ScoreRange { min: 0.0, max: 10.0, precision: 0 };
+        assert!(validate_score(&whole, 8.5).is_err());
+        assert_eq!(validate_score(&whole, 8.0).unwrap(), 8.0);
+    }
+
+    #[test]
+    fn fixed_point_scores_travel_as_plain_numbers() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Scored {
+            #[serde(with = "fixed_point")]
+            value: i32,
+            #[serde(with = "fixed_point::option")]
+            score: Option<i32>,
+        }
+
+        let scored: Scored = serde_json::from_value(serde_json::json!({ "value": 8.3, "score": null })).unwrap();
+        assert_eq!(scored, Scored { value: 8300, score: None });
+        let json = serde_json::to_value(Scored { value: 8250, score: Some(24750) }).unwrap();
+        assert_eq!(json, serde_json::json!({ "value": 8.25, "score": 24.75 }));
+        assert!(serde_json::from_value::<Scored>(serde_json::json!({ "value": 1.0e7, "score": null })).is_err());
+    }
+
+    #[test]
+    fn score_range_must_fit_the_stored_thousandths() {
+        let sum = policy(AggregationMode::Sum, 5, 0);
+        assert!(validate_score_range(0.0, 400_000.0, 0, &sum).is_ok());
+        assert!(validate_score_range(0.0, 500_000.0, 0, &sum).is_err());
+        assert!(validate_score_range(-500_000.0, 0.0, 0, &sum).is_err());
+        assert!(validate_score_range(0.0, 1.0e30, 0, &sum).is_err());
+        assert!(validate_score_range(0.0, 500_000.0, 0, &policy(AggregationMode::TrimmedSum, 5, 2)).is_ok());
+        assert!(validate_score_range(0.0, 2_000_000.0, 0, &policy(AggregationMode::Mean, 5, 0)).is_ok());
+        assert!(validate_score_range(0.0, 3_000_000.0, 0, &policy(AggregationMode::Mean, 5, 0)).is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use std::cmp::Ordering;
+use crate::{
+    error::AppError,
+    models::{ TieBreakResolution, TieBreakStep, TieBreakValue },
+    scoring::*,
+};
+
+pub const DEFAULT_TIE_BREAK_METHOD: &str = "high_low_coin";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreakMethod {
+    None,
+    HighLow,
+    HighLowCoin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreakStepKind {
+    DroppedHigh,
+    DroppedLow,
+    CoinFlip,
+}
+
+impl TieBreakMethod {
+    pub fn parse(value: &str) -> Option<TieBreakMethod> {
+        return match value {
+            "none" => Some(TieBreakMethod::None),
+            "high_low" => Some(TieBreakMethod::HighLow),
+            "high_low_coin" => Some(TieBreakMethod::HighLowCoin),
+            _ => None,
+        };
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            TieBreakMethod::None => "none",
+            TieBreakMethod::HighLow => "high_low",
+            TieBreakMethod::HighLowCoin => "high_low_coin",
+        };
+    }
+
+    pub fn steps(&self) -> &'static [TieBreakStepKind] {
+        return match self {
+            TieBreakMethod::None => &[],
+            TieBreakMethod::HighLow => &[TieBreakStepKind::DroppedHigh, TieBreakStepKind::DroppedLow],
+            TieBreakMethod::HighLowCoin =>
+                &[TieBreakStepKind::DroppedHigh, TieBreakStepKind::DroppedLow, TieBreakStepKind::CoinFlip],
+        };
+    }
+}
+
+impl TieBreakStepKind {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            TieBreakStepKind::DroppedHigh => "dropped_high",
+            TieBreakStepKind::DroppedLow => "dropped_low",
+            TieBreakStepKind::CoinFlip => "coin_flip",
+        };
+    }
+}
+
+pub fn validate_tie_break_method(value: &str) -> Result<TieBreakMethod, AppError> {
+    return TieBreakMethod::parse(value).ok_or_else(||
+        AppError::validation(
+            format!("tie_break_method must be one of none, high_low, high_low_coin (got {})", value)
+        )
+    );
+}
+
+/// Everything a poet can be separated on, in the order slam rules apply it.
+#[derive(Debug, Clone, Default)]
+pub struct TieBreakInput {
+    pub participant_id: String,
+    pub net_score: Option<f32>,
+    pub dropped_high: Option<f32>,
+    pub dropped_low: Option<f32>,
+    pub coin_flip: Option<i32>,
+}
+
+impl TieBreakInput {
+    fn value(&self, step: TieBreakStepKind) -> Option<f32> {
+        return match step {
+            TieBreakStepKind::DroppedHigh => self.dropped_high,
+            TieBreakStepKind::DroppedLow => self.dropped_low,
+            TieBreakStepKind::CoinFlip => self.coin_flip.map(|draw| draw as f32),
+        };
+    }
+
+    fn keys(&self, steps: &[TieBreakStepKind]) -> Option<Vec<i64>> {
+        let net_score = self.net_score?;
+        let mut keys = vec![score_key(net_score)];
+        for step in steps {
+            keys.push(self.value(*step).map_or(i64::MIN, score_key));
+        }
+        return Some(keys);
+    }
+}
+
+/// Sums of the scores a trimmed aggregation threw away, as `(high, low)`.
+/// Other aggregation modes drop nothing, so there is nothing to add back.
+pub fn dropped_scores(policy: &ScoringPolicy, values: &[f32]) -> (Option<f32>, Option<f32>) {
+    let drop = policy.drop_count as usize;
+    if policy.aggregation_mode != AggregationMode::TrimmedSum || drop == 0 || values.len() < drop * 2 {
+        return (None, None);
+    }
+    let low = values.iter().take(drop).sum();
+    let high = values.iter().rev().take(drop).sum();
+    return (Some(high), Some(low));
+}
+
+#[derive(Debug, Clone)]
+pub struct Ranked<T> {
+    pub rank: Option<i32>,
+    pub tied: bool,
+    pub item: T,
+}
+
+/// Scores are compared in thousandths so float noise from aggregation never splits a tie.
+pub fn score_key(score: f32) -> i64 {
+    return to_fixed(score);
+}
+
+/// Competition ranking (1, 1, 3) by descending score. Items without a score
+/// are kept at the end, unranked, in their original order.
+pub fn rank<T>(items: Vec<T>, score: impl Fn(&T) -> Option<f32>) -> Vec<Ranked<T>> {
+    return rank_by_keys(items, |item| score(item).map(|value| vec![score_key(value)]));
+}
+
+/// Competition ranking by descending keys, compared lexicographically.
+pub fn rank_by_keys<T>(items: Vec<T>, keys: impl Fn(&T) -> Option<Vec<i64>>) -> Vec<Ranked<T>> {
+    let mut keyed: Vec<(Option<Vec<i64>>, T)> = items
+        .into_iter()
+        .map(|item| (keys(&item), item))
+        .collect();
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        match (a, b) {
+            (Some(a), Some(b)) => b.cmp(a),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    });
+
+    let keys: Vec<Option<Vec<i64>>> = keyed
+        .iter()
+        .map(|(key, _)| key.clone())
+        .collect();
+
+    let mut results = Vec::new();
+    let mut current_rank = 0;
+    for (pos, (key, item)) in keyed.into_iter().enumerate() {
+        if key.is_none() {
+            results.push(Ranked { rank: None, tied: false, item });
+            continue;
+        }
+        if pos == 0 || keys[pos - 1] != key {
+            current_rank = (pos as i32) + 1;
+        }
+        let tied =
+            (pos > 0 && keys[pos - 1] == key) || (pos + 1 < keys.len() && keys[pos + 1] == key);
+        results.push(Ranked { rank: Some(current_rank), tied, item });
+    }
+
+    return results;
+}
+
+/// Groups of participants who are still level after every step before the
+/// coin flip, and therefore need a draw to separate them.
+pub fn needs_coin_flip<T>(method: TieBreakMethod, items: &[(TieBreakInput, T)]) -> Vec<Vec<String>> {
+    if !method.steps().contains(&TieBreakStepKind::CoinFlip) {
+        return Vec::new();
+    }
+    let steps: Vec<TieBreakStepKind> = method
+        .steps()
+        .iter()
+        .copied()
+        .filter(|step| *step != TieBreakStepKind::CoinFlip)
+        .collect();
+
+    let mut groups: Vec<(i32, Vec<String>)> = Vec::new();
+    for ranked in rank_by_keys(items.iter().collect(), |(input, _)| input.keys(&steps)) {
+        let (Some(rank), true) = (ranked.rank, ranked.tied) else {
+            continue;
+        };
+        let participant_id = ranked.item.0.participant_id.clone();
+        match groups.last_mut() {
+            Some((group_rank, group)) if *group_rank == rank => group.push(participant_id),
+            _ => groups.push((rank, vec![participant_id])),
+        }
+    }
+    return groups
+        .into_iter()
+        .map(|(_, group)| group)
+        .collect();
+}
+
+/// Ranks by net score and applies the tie-break steps in order. Alongside the
+/// final ranking, every group that was level on net score gets a record of the
+/// steps it took to separate them.
+pub fn rank_with_tie_breaks<T>(
+    method: TieBreakMethod,
+    items: Vec<(TieBreakInput, T)>
+) -> (Vec<Ranked<T>>, Vec<TieBreakResolution>) {
+    let steps = method.steps();
+    let ranked = rank_by_keys(items, |(input, _)| input.keys(steps));
+
+    let mut resolutions = Vec::new();
+    let mut pos = 0;
+    while pos < ranked.len() {
+        let net_key = ranked[pos].item.0.net_score.map(score_key);
+        let group_end =
+            pos +
+            ranked[pos..]
+                .iter()
+                .take_while(|other| other.item.0.net_score.map(score_key) == net_key)
+                .count();
+
+        if net_key.is_some() && group_end - pos > 1 {
+            let group: Vec<&TieBreakInput> = ranked[pos..group_end]
+                .iter()
+                .map(|member| &member.item.0)
+                .collect();
+
+            let mut resolution = TieBreakResolution {
+                rank: ranked[pos].rank.unwrap_or_default(),
+                participant_ids: group
+                    .iter()
+                    .map(|input| input.participant_id.clone())
+                    .collect(),
+                steps: Vec::new(),
+                resolved: false,
+            };
+
+            for (step_index, step) in steps.iter().enumerate() {
+                resolution.steps.push(TieBreakStep {
+                    method: step.as_str().to_owned(),
+                    values: group
+                        .iter()
+                        .map(|input| TieBreakValue {
+                            participant_id: input.participant_id.clone(),
+                            value: input.value(*step),
+                        })
+                        .collect(),
+                });
+
+                let mut prefixes: Vec<Option<Vec<i64>>> = group
+                    .iter()
+                    .map(|input| input.keys(&steps[..=step_index]))
+                    .collect();
+                prefixes.sort();
+                prefixes.dedup();
+                if prefixes.len() == group.len() {
+                    resolution.resolved = true;
+                    break;
+                }
+            }
+
+            resolutions.push(resolution);
+        }
+
+        pos = group_end;
+    }
+
+    let results = ranked
+        .into_iter()
+        .map(|ranked| Ranked { rank: ranked.rank, tied: ranked.tied, item: ranked.item.1 })
+        .collect();
+
+    return (results, resolutions);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranks<T>(ranked: &[Ranked<T>]) -> Vec<Option<i32>> {
+        return ranked
+            .iter()
+            .map(|ranked| ranked.rank)
+            .collect();
+    }
+
+    #[test]
+    fn rank_uses_competition_ranking_and_keeps_unscored_last() {
+        let ranked = rank(vec![Some(27.0), None, Some(28.5), Some(27.0), Some(26.0)], |score| *score);
+        assert_eq!(ranks(&ranked), vec![Some(1), Some(2), Some(2), Some(4), None]);
+        let tied: Vec<bool> = ranked
+            .iter()
+            .map(|ranked| ranked.tied)
+            .collect();
+        assert_eq!(tied, vec![false, true, true, false, false]);
+    }
+
+    #[test]
+    fn rank_does_not_split_ties_on_float_noise() {
+        let ranked = rank(vec![0.1_f32 + 0.2, 0.3], |score| Some(*score));
+        assert_eq!(ranks(&ranked), vec![Some(1), Some(1)]);
+    }
+
+    fn input(participant_id: &str, net_score: f32, high: f32, low: f32) -> TieBreakInput {
+        return TieBreakInput {
+            participant_id: participant_id.to_owned(),
+            net_score: Some(net_score),
+            dropped_high: Some(high),
+            dropped_low: Some(low),
+            coin_flip: None,
+        };
+    }
+
+    #[test]
+    fn dropped_scores_only_apply_to_trimmed_sum() {
+        let trimmed = ScoringPolicy {
+            judge_count: 5,
+            drop_count: 1,
+            aggregation_mode: AggregationMode::TrimmedSum,
+        };
+        assert_eq!(dropped_scores(&trimmed, &[7.0, 8.0, 8.5, 9.0, 9.5]), (Some(9.5), Some(7.0)));
+
+        let sum = ScoringPolicy { aggregation_mode: AggregationMode::Sum, ..trimmed };
+        assert_eq!(dropped_scores(&sum, &[7.0, 8.0, 8.5, 9.0, 9.5]), (None, None));
+    }
+
+    #[test]
+    fn tie_break_ladder_stops_at_the_first_separating_step() {
+        let items = vec![
+            (input("a", 27.0, 9.0, 7.0), "a"),
+            (input("b", 27.0, 9.5, 7.0), "b"),
+            (input("c", 25.0, 9.0, 6.0), "c")
+        ];
+        let (ranked, resolutions) = rank_with_tie_breaks(TieBreakMethod::HighLowCoin, items);
+
+        let order: Vec<&str> = ranked
+            .iter()
+            .map(|ranked| ranked.item)
+            .collect();
+        assert_eq!(order, vec!["b", "a", "c"]);
+        assert_eq!(ranks(&ranked), vec![Some(1), Some(2), Some(3)]);
+
+        assert_eq!(resolutions.len(), 1);
+        assert_eq!(resolutions[0].rank, 1);
+        assert!(resolutions[0].resolved);
+        assert_eq!(resolutions[0].steps.len(), 1);
+        assert_eq!(resolutions[0].steps[0].method, "dropped_high");
+    }
+
+    #[test]
+    fn tie_break_ladder_falls_through_to_the_low_score() {
+        let items = vec![
+            (input("a", 27.0, 9.0, 7.0), "a"),
+            (input("b", 27.0, 9.0, 7.5), "b")
+        ];
+        let (ranked, resolutions) = rank_with_tie_breaks(TieBreakMethod::HighLow, items);
+        assert_eq!(ranked[0].item, "b");
+        assert!(resolutions[0].resolved);
+        assert_eq!(resolutions[0].steps.len(), 2);
+        assert_eq!(resolutions[0].steps[1].method, "dropped_low");
+    }
+
+    #[test]
+    fn unresolved_tie_waits_for_a_coin_flip() {
+        let items = vec![
+            (input("a", 27.0, 9.0, 7.0), "a"),
+            (input("b", 27.0, 9.0, 7.0), "b"),
+            (input("c", 26.0, 9.0, 7.0), "c")
+        ];
+        assert_eq!(needs_coin_flip(TieBreakMethod::HighLowCoin, &items), vec![vec!["a", "b"]]);
+        assert!(needs_coin_flip(TieBreakMethod::HighLow, &items).is_empty());
+
+        let (ranked, resolutions) = rank_with_tie_breaks(TieBreakMethod::HighLowCoin, items);
+        assert_eq!(ranks(&ranked), vec![Some(1), Some(1), Some(3)]);
+        assert!(!resolutions[0].resolved);
+        assert_eq!(resolutions[0].steps.len(), 3);
+    }
+
+    #[test]
+    fn coin_flip_draws_separate_the_remaining_tie() {
+        let mut a = input("a", 27.0, 9.0, 7.0);
+        let mut b = input("b", 27.0, 9.0, 7.0);
+        a.coin_flip = Some(0);
+        b.coin_flip = Some(1);
+        let (ranked, resolutions) = rank_with_tie_breaks(
+            TieBreakMethod::HighLowCoin,
+            vec![(a, "a"), (b, "b")]
+        );
+        assert_eq!(ranked[0].item, "b");
+        assert_eq!(ranks(&ranked), vec![Some(1), Some(2)]);
+        assert!(resolutions[0].resolved);
+    }
+
+    #[test]
+    fn tie_break_method_none_leaves_ties_standing() {
+        let items = vec![
+            (input("a", 27.0, 9.0, 7.0), "a"),
+            (input("b", 27.0, 9.5, 7.0), "b")
+        ];
+        let (ranked, resolutions) = rank_with_tie_breaks(TieBreakMethod::None, items);
+        assert_eq!(ranks(&ranked), vec![Some(1), Some(1)]);
+        assert!(!resolutions[0].resolved);
+        assert!(resolutions[0].steps.is_empty());
+    }
+
+    #[test]
+    fn unknown_tie_break_method_is_rejected() {
+        assert!(validate_tie_break_method("coin").is_err());
+        assert_eq!(validate_tie_break_method("high_low").unwrap(), TieBreakMethod::HighLow);
+    }
+
+    #[test]
+    fn separate_ties_need_separate_coin_flips() {
+        let items = vec![
+            (input("a", 27.0, 9.0, 7.0), "a"),
+            (input("b", 27.0, 9.0, 7.0), "b"),
+            (input("c", 25.0, 9.0, 7.0), "c"),
+            (input("d", 25.0, 9.0, 7.0), "d"),
+            (input("e", 24.0, 9.0, 7.0), "e")
+        ];
+        assert_eq!(needs_coin_flip(TieBreakMethod::HighLowCoin, &items), vec![vec!["a", "b"], vec!["c", "d"]]);
+    }
+}
@@ -0,0 +1,218 @@
+use std::{ collections::HashMap, sync::{ atomic::{ AtomicU64, Ordering }, Arc, Mutex }, time::Duration };
+use chrono::{ DateTime, Utc };
+use tokio::task::JoinHandle;
+use crate::{ channels::RoomChannels, error::AppError, models::RoomEvent };
+
+pub const TIMER_TICK_SECONDS: u64 = 1;
+/// A timer nobody stopped stops ticking on its own after this long.
+pub const MAX_TIMER_SECONDS: i64 = 60 * 60;
+
+pub fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, AppError> {
+    return DateTime::parse_from_rfc3339(value)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|error| AppError::Internal(format!("invalid timestamp {}: {}", value, error)));
+}
+
+/// Whole seconds between two stored timestamps, rounded down like a stopwatch.
+pub fn elapsed_seconds(started_at: &str, stopped_at: &str) -> Result<i32, AppError> {
+    let elapsed = parse_timestamp(stopped_at)? - parse_timestamp(started_at)?;
+    return Ok(elapsed.num_seconds().max(0) as i32);
+}
+
+struct RunningTimer {
+    /// Tells this run apart from a later restart of the same participation's timer.
+    generation: u64,
+    room_id: String,
+    participant_id: String,
+    handle: JoinHandle<()>,
+}
+
+/// Tick tasks of the running timers, by participation id. These only live in
+/// memory: after a restart a running timer still stops from its stored start
+/// time, it just no longer ticks.
+#[derive(Default)]
+pub struct Timers {
+    running: Arc<Mutex<HashMap<String, RunningTimer>>>,
+    generations: AtomicU64,
+}
+
+impl Timers {
+    /// Sends the running time to the room every tick until `stop` is called,
+    /// or until `MAX_TIMER_SECONDS` have passed.
+    pub fn start(
+        &self,
+        channels: Arc<RoomChannels>,
+        room_id: &str,
+        participant_id: &str,
+        participation_id: &str,
+        started_at: DateTime<Utc>
+    ) {
+        let generation = self.generations.fetch_add(1, Ordering::Relaxed);
+        let running = self.running.clone();
+        let room_id_value = room_id.to_owned();
+        let participation_id_value = participation_id.to_owned();
+
+        // Held until the new entry is in, so a task that stops at once can't
+        // try to remove itself before it has been added.
+        let mut timers = self.running.lock().unwrap();
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(TIMER_TICK_SECONDS));
+            loop {
+                interval.tick().await;
+                let elapsed = (Utc::now() - started_at).num_seconds().max(0);
+                if elapsed > MAX_TIMER_SECONDS {
+                    break;
+                }
+                channels.send_transient(&room_id_value, RoomEvent::TimerTick {
+                    participation_id: participation_id_value.clone(),
+                    elapsed_seconds: elapsed as i32,
+                });
+            }
+
+            let mut timers = running.lock().unwrap();
+            if timers.get(&participation_id_value).is_some_and(|timer| timer.generation == generation) {
+                timers.remove(&participation_id_value);
+            }
+        });
+
+        let timer = RunningTimer {
+            generation,
+            room_id: room_id.to_owned(),
+            participant_id: participant_id.to_owned(),
+            handle,
+        };
+        if let Some(previous) = timers.insert(participation_id.to_owned(), timer) {
+            previous.handle.abort();
+        }
+    }
+
+    pub fn stop(&self, participation_id: &str) {
+        if let Some(timer) = self.running.lock().unwrap().remove(participation_id) {
+            timer.handle.abort();
+        }
+    }
+
+    /// Stops every timer in a room that is being deleted.
+    pub fn stop_room(&self, room_id: &str) {
+        self.stop_where(|timer| timer.room_id == room_id);
+    }
+
+    /// Stops the timers of a participant who is being deleted.
+    pub fn stop_participant(&self, participant_id: &str) {
+        self.stop_where(|timer| timer.participant_id == participant_id);
+    }
+
+    fn stop_where(&self, matches: impl Fn(&RunningTimer) -> bool) {
+        self.running.lock().unwrap().retain(|_, timer| {
+            if matches(timer) {
+                timer.handle.abort();
+                return false;
+            }
+            return true;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channels::{ CHANNEL_CAPACITY, EVENT_HISTORY_IDLE, EVENT_HISTORY_SIZE };
+
+    fn channels() -> Arc<RoomChannels> {
+        return Arc::new(RoomChannels::new(CHANNEL_CAPACITY, EVENT_HISTORY_SIZE, EVENT_HISTORY_IDLE));
+    }
+
+    fn running(timers: &Timers) -> Vec<String> {
+        let mut ids: Vec<String> = timers.running.lock().unwrap().keys().cloned().collect();
+        ids.sort();
+        return ids;
+    }
+
+    #[test]
+    fn elapsed_seconds_rounds_down() {
+        let elapsed = elapsed_seconds("2024-05-01T20:00:00.000Z", "2024-05-01T20:03:10.999Z");
+        assert_eq!(elapsed.unwrap(), 190);
+    }
+
+    #[test]
+    fn elapsed_seconds_handles_offsets() {
+        let elapsed = elapsed_seconds("2024-05-01T20:00:00+00:00", "2024-05-01T22:01:00+02:00");
+        assert_eq!(elapsed.unwrap(), 60);
+    }
+
+    #[test]
+    fn elapsed_seconds_never_goes_negative() {
+        let elapsed = elapsed_seconds("2024-05-01T20:00:05Z", "2024-05-01T20:00:00Z");
+        assert_eq!(elapsed.unwrap(), 0);
+    }
+
+    #[test]
+    fn unparseable_timestamps_are_rejected() {
+        assert!(matches!(parse_timestamp("yesterday"), Err(AppError::Internal(_))));
+        assert!(elapsed_seconds("2024-05-01T20:00:00Z", "").is_err());
+    }
+
+    #[tokio::test]
+    async fn running_timers_tick_until_stopped() {
+        let timers = Timers::default();
+        let channels = channels();
+        let (mut subscription, _) = channels.subscribe("room-1");
+        timers.start(channels.clone(), "room-1", "poet-1", "participation-1", Utc::now());
+        timers.start(channels.clone(), "room-1", "poet-2", "participation-2", Utc::now());
+
+        let tick = subscription.recv().await.unwrap();
+        assert!(tick.transient);
+        assert!(tick.text.contains("timer_tick"));
+
+        timers.stop("participation-1");
+        assert_eq!(running(&timers), vec!["participation-2"]);
+        timers.stop("participation-2");
+        assert!(running(&timers).is_empty());
+    }
+
+    #[tokio::test]
+    async fn stopping_a_room_or_participant_stops_only_their_timers() {
+        let timers = Timers::default();
+        let channels = channels();
+        timers.start(channels.clone(), "room-1", "poet-1", "participation-1", Utc::now());
+        timers.start(channels.clone(), "room-1", "poet-2", "participation-2", Utc::now());
+        timers.start(channels.clone(), "room-2", "poet-3", "participation-3", Utc::now());
+
+        timers.stop_participant("poet-2");
+        assert_eq!(running(&timers), vec!["participation-1", "participation-3"]);
+
+        timers.stop_room("room-1");
+        assert_eq!(running(&timers), vec!["participation-3"]);
+
+        timers.stop("participation-3");
+        assert!(running(&timers).is_empty());
+    }
+
+    #[tokio::test]
+    async fn finished_timers_remove_themselves() {
+        let timers = Timers::default();
+        let started_at = Utc::now() - chrono::Duration::seconds(MAX_TIMER_SECONDS + 1);
+        timers.start(channels(), "room-1", "poet-1", "participation-1", started_at);
+
+        for _ in 0..100 {
+            if running(&timers).is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(running(&timers).is_empty());
+    }
+
+    #[tokio::test]
+    async fn restarting_a_timer_replaces_the_running_one() {
+        let timers = Timers::default();
+        let channels = channels();
+        let finished = Utc::now() - chrono::Duration::seconds(MAX_TIMER_SECONDS + 1);
+        timers.start(channels.clone(), "room-1", "poet-1", "participation-1", finished);
+        timers.start(channels, "room-1", "poet-1", "participation-1", Utc::now());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(running(&timers), vec!["participation-1"]);
+        timers.stop("participation-1");
+    }
+}